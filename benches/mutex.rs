@@ -0,0 +1,85 @@
+//! Compares `Mutex` against a mutex built on a binary `Semaphore`, the way `Mutex` used to be implemented,
+//! and against `std::sync::Mutex`. Run with `cargo bench --bench mutex`.
+
+use semaphore_rust::{Mutex, Semaphore};
+use std::cell::UnsafeCell;
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 1_000_000;
+const THREADS: u32 = 4;
+
+/// The lock operations the benchmarks need, implemented for each mutex compared.
+trait Lock: Sync {
+    fn with(&self, f: impl FnOnce(&mut u64));
+}
+
+impl Lock for Mutex<u64> {
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock().unwrap());
+    }
+}
+
+impl Lock for std::sync::Mutex<u64> {
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock().unwrap());
+    }
+}
+
+/// A mutex that locks by signalling a `Semaphore::init(0, 1)` and unlocks by waiting on it.
+struct SemaphoreMutex {
+    semaphore: Semaphore,
+    data: UnsafeCell<u64>,
+}
+
+// Safety: the data is only accessed while the semaphore is at 1
+unsafe impl Sync for SemaphoreMutex {}
+
+impl Lock for SemaphoreMutex {
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        self.semaphore.signal().unwrap();
+        // Safety: we hold the lock
+        f(unsafe { &mut *self.data.get() });
+        self.semaphore.wait().unwrap();
+    }
+}
+
+/// Locks and unlocks `lock` `ITERATIONS` times from a single thread.
+fn uncontended(lock: &impl Lock) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        lock.with(|value| *value = black_box(*value + 1));
+    }
+    start.elapsed()
+}
+
+/// Locks and unlocks `lock` `ITERATIONS` times in total, split over `THREADS` threads.
+fn contended(lock: &impl Lock) -> Duration {
+    let start = Instant::now();
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for _ in 0..ITERATIONS / THREADS {
+                    lock.with(|value| *value = black_box(*value + 1));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn report(name: &str, lock: &impl Lock) {
+    let per_op = |elapsed: Duration| elapsed.as_nanos() as f64 / ITERATIONS as f64;
+    println!(
+        "{name:<20} uncontended {:>8.1} ns/op    contended ({THREADS} threads) {:>8.1} ns/op",
+        per_op(uncontended(lock)),
+        per_op(contended(lock)),
+    );
+}
+
+fn main() {
+    report("Mutex", &Mutex::new(0));
+    report("Semaphore mutex", &SemaphoreMutex { semaphore: Semaphore::init(0, 1), data: UnsafeCell::new(0) });
+    report("std::sync::Mutex", &std::sync::Mutex::new(0));
+}
@@ -0,0 +1,58 @@
+use std::cell::UnsafeCell;
+use std::ops::{Deref, DerefMut};
+
+use crate::semaphore::{Semaphore, SemaphorePermit};
+
+
+/// Async version of `Mutex`, whose `lock` returns a future instead of blocking the current thread.
+/// Its guard may be held across `.await` points.
+pub struct AsyncMutex<T> {
+    /// Binary semaphore like the one of `Mutex`, but counting down from 1, so locking is taking its only unit
+    semaphore: Semaphore,
+    data: UnsafeCell<T>,
+}
+
+impl<T> AsyncMutex<T> {
+    /// Associated method for creating a new `AsyncMutex`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            semaphore: Semaphore::init(1, 1),
+            data: UnsafeCell::new(value),
+        }
+    }
+    /// Method for locking the mutex. If the lock is unsuccessful the returned future stays pending
+    /// until the mutex is unlocked and it is next in line. Dropping the future gives up its place in line,
+    /// passing the lock on if it was already handed to the future.
+    pub async fn lock(&self) -> AsyncMutexGuard<'_, T> {
+        let permit = self.semaphore.acquire_async().await.expect("the semaphore of a mutex is never closed");
+        AsyncMutexGuard { mutex: self, _permit: permit }
+    }
+}
+
+unsafe impl<T> Sync for AsyncMutex<T> where T: Send {}
+unsafe impl<T> Send for AsyncMutex<T> where T: Send {}
+
+/// A guard for `AsyncMutex<T>`. Ensures thread/memory safety of the data held by an `AsyncMutex`
+pub struct AsyncMutexGuard<'a, T> {
+    mutex: &'a AsyncMutex<T>,
+    /// Gives the unit of the semaphore back when dropped, unlocking the `AsyncMutex`
+    _permit: SemaphorePermit<'a>,
+}
+
+// Sharing the guard shares the data, which the auto trait derived from `&AsyncMutex<T>` would allow for any `T: Send`
+unsafe impl<T> Sync for AsyncMutexGuard<'_, T> where T: Sync {}
+
+impl<T> Deref for AsyncMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have an `AsyncMutexGuard` we know we have exclusive access to the data
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for AsyncMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: if we have an `AsyncMutexGuard` we know we have exclusive access to the data
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
@@ -0,0 +1,36 @@
+use rand::Rng;
+use semaphore_rust::{Condvar, Mutex};
+use std::thread;
+
+
+fn main() {
+    use std::collections::VecDeque;
+    let q = VecDeque::new();
+    let mutex = &Mutex::new(q);
+    let not_empty = &Condvar::new();
+
+    thread::scope(|s| {
+        for i in 0..10 {
+            s.spawn(move || {
+                let mut rng = rand::thread_rng();
+                for _j in 0..100 {
+                    let num = rng.gen_range(i * 10 ..= (i + 1) * 10);
+                    mutex.lock().unwrap().push_back(num);
+                    not_empty.notify_one();
+                }
+            });
+        }
+
+        let mut counter = 0;
+        while counter < 1000 {
+            // Sleep until a producer pushed something rather than spinning on the lock
+            let mut queue = not_empty.wait_while(mutex.lock().unwrap(), |queue| queue.is_empty()).unwrap();
+            let generated_num = queue.pop_front().unwrap();
+            drop(queue);
+            println!("generated_num: {generated_num}");
+            counter += 1;
+        }
+
+        println!("processed {counter} nums complete");
+    });
+}
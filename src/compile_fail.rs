@@ -0,0 +1,67 @@
+//! Compile-fail tests locking down the auto traits of the primitives, run as doctests.
+//!
+//! Initializing a `Semaphore` in a `static` with a count above its max fails to compile:
+//!
+//! ```compile_fail,E0080
+//! static SEMAPHORE: semaphore_rust::Semaphore = semaphore_rust::Semaphore::init(9, 8);
+//! ```
+//!
+//! A `Mutex` is only `Sync` if its data can be sent to the thread locking it:
+//!
+//! ```compile_fail,E0277
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<semaphore_rust::Mutex<std::rc::Rc<u32>>>();
+//! ```
+//!
+//! A `MutexGuard` has to unlock the `Mutex` on the thread that locked it:
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<semaphore_rust::MutexGuard<'static, u32>>();
+//! ```
+//!
+//! Sharing a `MutexGuard` shares the data, which requires the data to be `Sync`:
+//!
+//! ```compile_fail,E0277
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<semaphore_rust::MutexGuard<'static, std::cell::Cell<u32>>>();
+//! ```
+//!
+//! The same goes for a `MappedMutexGuard`, which keeps the `Mutex` locked:
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<semaphore_rust::MappedMutexGuard<'static, u32>>();
+//! ```
+//!
+//! Readers share the data of a `RwLock` across threads, so unlike `Mutex` it needs the data to be `Sync`:
+//!
+//! ```compile_fail,E0277
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<semaphore_rust::RwLock<std::cell::Cell<u32>>>();
+//! ```
+//!
+//! The guards of a `RwLock` have to unlock it on the thread that locked it:
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<semaphore_rust::RwLockReadGuard<'static, u32>>();
+//! ```
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<semaphore_rust::RwLockWriteGuard<'static, u32>>();
+//! ```
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<semaphore_rust::RwLockUpgradableReadGuard<'static, u32>>();
+//! ```
+//!
+//! A `ReentrantMutexGuard` has to unlock the `ReentrantMutex` on the thread that locked it, as the lock count
+//! belongs to that thread:
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<semaphore_rust::ReentrantMutexGuard<'static, u32>>();
+//! ```
@@ -0,0 +1,120 @@
+use atomic_wait::{wait, wake_all, wake_one};
+use std::fmt;
+use std::sync::LockResult;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering::Relaxed};
+use std::time::{Duration, Instant};
+
+use crate::futex;
+use crate::mutex::MutexGuard;
+use crate::poison;
+
+
+/// A condition variable, letting threads holding the lock of a `Mutex` sleep until another thread
+/// notifies them that the data protected by the `Mutex` changed.
+///
+/// Like `std::sync::Condvar` waiting threads may be woken up spuriously, so the condition they wait for
+/// has to be checked again after every wake up, which `wait_while` does.
+pub struct Condvar {
+    /// Incremented on every notification, waiting threads wait for it to change
+    counter: AtomicU32,
+    /// The number of threads waiting, so notifying nobody skips the wake up syscall
+    num_waiters: AtomicUsize,
+}
+
+impl Condvar {
+    /// Associated method for creating a new `Condvar`.
+    pub const fn new() -> Self {
+        Self {
+            counter: AtomicU32::new(0),
+            num_waiters: AtomicUsize::new(0),
+        }
+    }
+    /// Unlocks the `Mutex` of `guard` and blocks the current thread until it is notified, then locks the
+    /// `Mutex` again before returning.
+    ///
+    /// Errors: if the mutex is poisoned once it is locked again, the error holds the guard
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> LockResult<MutexGuard<'a, T>> {
+        let (guard, _) = self.wait_until(guard, None);
+        guard
+    }
+    /// Blocks the current thread like `wait` for as long as `condition` returns `true` for the data of the
+    /// `Mutex`, which is checked before waiting and after every wake up.
+    ///
+    /// Errors: if the mutex is poisoned once it is locked again, the error holds the guard
+    pub fn wait_while<'a, T, F>(&self, mut guard: MutexGuard<'a, T>, mut condition: F) -> LockResult<MutexGuard<'a, T>>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard)?;
+        }
+        Ok(guard)
+    }
+    /// Same as `wait`, but stops waiting once `timeout` has elapsed. The `Mutex` is locked again in either case,
+    /// the returned `WaitTimeoutResult` tells whether the wait timed out.
+    ///
+    /// Errors: if the mutex is poisoned once it is locked again, the error holds the guard and the result
+    pub fn wait_timeout<'a, T>(&self, guard: MutexGuard<'a, T>, timeout: Duration)
+        -> LockResult<(MutexGuard<'a, T>, WaitTimeoutResult)>
+    {
+        // A timeout this far in the future is no timeout at all
+        let (guard, timed_out) = self.wait_until(guard, Instant::now().checked_add(timeout));
+        poison::map_result(guard, |guard| (guard, WaitTimeoutResult(timed_out)))
+    }
+    /// Wakes up one of the threads waiting on the `Condvar`, if there are any.
+    pub fn notify_one(&self) {
+        if self.num_waiters.load(Relaxed) > 0 {
+            self.counter.fetch_add(1, Relaxed);
+            wake_one(&self.counter);
+        }
+    }
+    /// Wakes up all threads waiting on the `Condvar`.
+    pub fn notify_all(&self) {
+        if self.num_waiters.load(Relaxed) > 0 {
+            self.counter.fetch_add(1, Relaxed);
+            wake_all(&self.counter);
+        }
+    }
+    /// Helper method, waits for a notification until `deadline` is reached if there is one.
+    /// Returns the locked guard, and `true` if the wait timed out.
+    fn wait_until<'a, T>(&self, guard: MutexGuard<'a, T>, deadline: Option<Instant>) -> (LockResult<MutexGuard<'a, T>>, bool) {
+        // Registering as a waiter and reading the counter before unlocking, so that a notification sent
+        // right after unlocking is never missed. Notifiers change the data under the lock before notifying.
+        self.num_waiters.fetch_add(1, Relaxed);
+        let counter = self.counter.load(Relaxed);
+        let mutex = guard.mutex();
+        drop(guard);
+        let timed_out = match deadline {
+            Some(deadline) => !futex::wait_until(&self.counter, counter, deadline) || Instant::now() >= deadline,
+            None => {
+                wait(&self.counter, counter);
+                false
+            }
+        };
+        self.num_waiters.fetch_sub(1, Relaxed);
+        (mutex.lock(), timed_out)
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tells whether `Condvar::wait_timeout` returned because its timeout elapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait timed out rather than being notified.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
@@ -0,0 +1,74 @@
+//! Timed counterpart of `atomic_wait::wait`, which can only block indefinitely, and a counterpart of
+//! `atomic_wait::wake_one` telling whether a thread was woken.
+
+use std::sync::atomic::AtomicU32;
+use std::time::Instant;
+
+
+/// Blocks the current thread while `atomic` is equal to `expected`, until it is woken up by one of the
+/// `atomic_wait` wake functions or `deadline` is reached. Like `atomic_wait::wait` it may return spuriously.
+///
+/// Returns `false` if `deadline` has already passed, `true` otherwise.
+#[cfg(target_os = "linux")]
+pub(crate) fn wait_until(atomic: &AtomicU32, expected: u32, deadline: Instant) -> bool {
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    if timeout.is_zero() {
+        return false;
+    }
+    let timespec = libc::timespec {
+        tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    // Safety: the pointer refers to a live `AtomicU32` and `FUTEX_WAIT` only reads from it.
+    // `FUTEX_WAIT` takes a relative timeout, the return value is ignored since the caller always re-checks.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            atomic.as_ptr(),
+            libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+            expected,
+            &timespec as *const libc::timespec,
+        );
+    }
+    true
+}
+
+/// Fallback for platforms without a timed futex, polls `atomic` with short sleeps until `deadline`.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn wait_until(atomic: &AtomicU32, expected: u32, deadline: Instant) -> bool {
+    use std::sync::atomic::Ordering::Relaxed;
+    use std::time::Duration;
+
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    if timeout.is_zero() {
+        return false;
+    }
+    if atomic.load(Relaxed) == expected {
+        std::thread::sleep(timeout.min(Duration::from_millis(1)));
+    }
+    true
+}
+
+/// Wakes up one of the threads blocked on `atomic`, like `atomic_wait::wake_one`.
+///
+/// Returns `true` if a thread was known to be woken, `false` if no thread was blocked on `atomic`.
+#[cfg(target_os = "linux")]
+pub(crate) fn wake_one(atomic: &AtomicU32) -> bool {
+    // Safety: `FUTEX_WAKE` only uses the address of the `AtomicU32`, it returns the number of threads woken
+    let woken = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            atomic.as_ptr(),
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            1,
+        )
+    };
+    woken > 0
+}
+
+/// Fallback for platforms where the number of threads woken is unknown, never claims one was.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn wake_one(atomic: &AtomicU32) -> bool {
+    atomic_wait::wake_one(atomic);
+    false
+}
@@ -0,0 +1,27 @@
+//! Synchronization primitives built on top of `AtomicU32` and `atomic_wait`.
+//!
+//! [`Mutex`] and [`RwLock`] are futex based locks working on their own state, [`Semaphore`] is the building
+//! block of the other primitives such as [`AsyncMutex`].
+
+pub mod semaphore;
+pub mod mutex;
+pub mod reentrant_mutex;
+pub mod rwlock;
+pub mod condvar;
+pub mod async_mutex;
+pub mod executor;
+
+mod futex;
+mod poison;
+mod raw_mutex;
+mod wait_queue;
+
+#[cfg(doctest)]
+mod compile_fail;
+
+pub use semaphore::{Semaphore, SemaphorePermit, OwnedSemaphorePermit, Closed};
+pub use mutex::{Mutex, MutexGuard, MappedMutexGuard};
+pub use reentrant_mutex::{ReentrantMutex, ReentrantMutexGuard};
+pub use condvar::{Condvar, WaitTimeoutResult};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
+pub use async_mutex::{AsyncMutex, AsyncMutexGuard};
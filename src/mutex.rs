@@ -0,0 +1,325 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::{LockResult, PoisonError, TryLockError, TryLockResult};
+use std::time::{Duration, Instant};
+
+use crate::poison;
+use crate::raw_mutex::RawMutex;
+
+
+/// Basic implementation of a three state mutex. The state is either unlocked, locked, or locked with other
+/// threads waiting, so unlocking only wakes up a thread when there is one waiting. Contended threads spin
+/// for a while before parking, for as long as it took them on average to get the lock in the past.
+///
+/// Like `std::sync::Mutex` the mutex is poisoned if a thread panics while holding it, after which
+/// locking it returns a `PoisonError`. The guard can still be recovered with `PoisonError::into_inner`.
+pub struct Mutex<T> {
+    raw: RawMutex,
+    poison: poison::Flag,
+    data: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    /// Associated method for creating a new `Mutex`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            raw: RawMutex::new(),
+            poison: poison::Flag::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+    /// Method for locking the mutex. If the lock is unsuccessfully the current threads execution will
+    /// block, and wait until it is woken up.
+    ///
+    /// Errors: if the mutex is poisoned, the mutex is still locked and the error holds the guard
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        // Once we return from `self.raw.lock()` we know the mutex is locked
+        self.raw.lock();
+        MutexGuard::new(self)
+    }
+    /// Non-blocking version of `lock`. Fails with `TryLockError::WouldBlock` immediately if the mutex is
+    /// already locked, or with `TryLockError::Poisoned` if it was locked but is poisoned.
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+        self.locked_in_time(self.raw.try_lock())
+    }
+    /// Same as `lock`, but gives up and fails with `TryLockError::WouldBlock` once `timeout` has elapsed.
+    pub fn try_lock_for(&self, timeout: Duration) -> TryLockResult<MutexGuard<'_, T>> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.try_lock_until(deadline),
+            // A timeout this far in the future is no timeout at all
+            None => Ok(self.lock()?),
+        }
+    }
+    /// Same as `lock`, but gives up and fails with `TryLockError::WouldBlock` once `deadline` is reached.
+    pub fn try_lock_until(&self, deadline: Instant) -> TryLockResult<MutexGuard<'_, T>> {
+        self.locked_in_time(self.raw.lock_until(deadline))
+    }
+    /// Returns `true` if a thread panicked while holding the mutex.
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+    /// Clears the poisoned state of the mutex, for when the data was recovered and is known to be valid again.
+    pub fn clear_poison(&self) {
+        self.poison.clear();
+    }
+    /// Consumes the mutex, returning its data.
+    ///
+    /// Errors: if the mutex is poisoned, the error holds the data
+    pub fn into_inner(self) -> LockResult<T> {
+        let poisoned = self.poison.get();
+        let data = self.data.into_inner();
+        if poisoned {
+            Err(PoisonError::new(data))
+        } else {
+            Ok(data)
+        }
+    }
+    /// Returns a mutable reference to the data. No locking is needed, the mutable borrow of the mutex
+    /// guarantees nobody else has access to it.
+    ///
+    /// Errors: if the mutex is poisoned, the error holds the reference
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let data = self.data.get_mut();
+        if self.poison.get() {
+            Err(PoisonError::new(data))
+        } else {
+            Ok(data)
+        }
+    }
+    /// Helper method, creates the guard of the non-blocking variants of `lock`, if the mutex was `locked` in time.
+    fn locked_in_time(&self, locked: bool) -> TryLockResult<MutexGuard<'_, T>> {
+        if locked {
+            Ok(MutexGuard::new(self)?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Mutex");
+        // Only peeks at the data if the mutex is unlocked, formatting must never block
+        match self.try_lock() {
+            Ok(guard) => debug.field("data", &&*guard),
+            Err(TryLockError::Poisoned(error)) => debug.field("data", &&**error.get_ref()),
+            Err(TryLockError::WouldBlock) => debug.field("data", &format_args!("<locked>")),
+        };
+        debug.field("poisoned", &self.poison.get());
+        debug.finish_non_exhaustive()
+    }
+}
+
+// Like `std::sync::Mutex`, only `T: Send` is required, the mutex never hands out shared access to the data
+unsafe impl<T> Sync for Mutex<T> where T: Send {}
+unsafe impl<T> Send for Mutex<T> where T: Send {}
+
+/// A guard for `Mutex<T>`. Ensures thread/memory safety of the data held by a `Mutex`
+///
+/// The guard is not `Send`, like `std::sync::MutexGuard` it has to unlock the `Mutex` on the thread that locked it.
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+    poison: poison::Guard,
+    _not_send: PhantomData<*const ()>,
+}
+
+// Sharing the guard only shares the data, the raw pointer marker opts out of `Sync` as well as `Send`
+unsafe impl<T> Sync for MutexGuard<'_, T> where T: Sync {}
+
+impl<'a, T> MutexGuard<'a, T> {
+    /// Associated function, wraps a locked `mutex` in a guard, failing if it is poisoned.
+    fn new(mutex: &'a Mutex<T>) -> LockResult<Self> {
+        poison::map_result(mutex.poison.guard(), |poison| MutexGuard { mutex, poison, _not_send: PhantomData })
+    }
+    /// The `Mutex` the guard locks, for `Condvar` to unlock and lock it again while waiting.
+    pub(crate) fn mutex(&self) -> &'a Mutex<T> {
+        self.mutex
+    }
+    /// Unlocks the `Mutex` while `f` runs and locks it again afterwards, also if `f` panics.
+    /// Other threads can lock the `Mutex` in the meantime, so the data may have changed once `f` returns.
+    ///
+    /// This is an associated function, called as `MutexGuard::unlocked(&mut guard, f)`.
+    /// The guard keeps its access to the data even if another thread poisons the `Mutex` while it is unlocked.
+    pub fn unlocked<R>(guard: &mut Self, f: impl FnOnce() -> R) -> R {
+        /// Locks the `Mutex` again when dropped, so it is locked when the guard is dropped even if `f` panics
+        struct Relock<'a, 'b, T>(&'b mut MutexGuard<'a, T>);
+
+        impl<T> Drop for Relock<'_, '_, T> {
+            fn drop(&mut self) {
+                self.0.mutex.raw.lock();
+                // A panic in `f` happened while the `Mutex` was unlocked, so it must not poison it
+                self.0.poison = self.0.mutex.poison.guard().unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+
+        guard.mutex.poison.done(&guard.poison);
+        // Safety: if we have a `MutexGuard` we know we hold the lock, `Relock` takes it again before the guard is used
+        unsafe { guard.mutex.raw.unlock() };
+        let _relock = Relock(guard);
+        f()
+    }
+    /// Unlocks the `Mutex` like dropping the guard does, but hands the lock over to a thread waiting for it
+    /// rather than letting a thread that did not wait yet take it first.
+    ///
+    /// This is an associated function, called as `MutexGuard::unlock_fair(guard)`.
+    pub fn unlock_fair(guard: Self) {
+        let guard = ManuallyDrop::new(guard);
+        guard.mutex.poison.done(&guard.poison);
+        // Safety: if we have a `MutexGuard` we know we hold the lock, and it is never dropped to unlock it twice
+        unsafe { guard.mutex.raw.unlock_fair() };
+    }
+    /// Turns the guard into a guard for a part of the data, such as one of its fields. The `Mutex` stays
+    /// locked until the returned guard is dropped, but only the part returned by `f` is accessible.
+    ///
+    /// This is an associated function, called as `MutexGuard::map(guard, f)`, so it can't clash with a method of `T`.
+    pub fn map<U>(guard: Self, f: impl FnOnce(&mut T) -> &mut U) -> MappedMutexGuard<'a, U> {
+        // Safety: if we have a `MutexGuard` we know we have exclusive access to the data for as long as it is locked
+        let data = f(unsafe { &mut *guard.mutex.data.get() });
+        // If `f` panicked the guard was dropped, unlocking and poisoning the `Mutex`
+        MappedMutexGuard::new(guard, data)
+    }
+    /// Same as `map`, but `f` may fail to find the part of the data, in which case the original guard is returned.
+    ///
+    /// This is an associated function, called as `MutexGuard::try_map(guard, f)`.
+    pub fn try_map<U>(guard: Self, f: impl FnOnce(&mut T) -> Option<&mut U>) -> Result<MappedMutexGuard<'a, U>, Self> {
+        // Safety: if we have a `MutexGuard` we know we have exclusive access to the data for as long as it is locked
+        match f(unsafe { &mut *guard.mutex.data.get() }) {
+            Some(data) => Ok(MappedMutexGuard::new(guard, data)),
+            None => Err(guard),
+        }
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have a `MutexGuard` we know we have exclusive access to the data
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: if we have a `MutexGuard` we know we have exclusive access to the data
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        // Poison the `Mutex` if we are unwinding from a panic, the data may be left half updated
+        self.mutex.poison.done(&self.poison);
+        // Safety: if we have a `MutexGuard` we know we hold the lock
+        unsafe { self.mutex.raw.unlock() };
+    }
+}
+
+/// A guard for a part of the data of a locked `Mutex`, returned by `MutexGuard::map` and `MutexGuard::try_map`.
+/// Unlocks the `Mutex` when dropped, and poisons it if a panic happens while it is alive, like `MutexGuard`.
+///
+/// The guard is not `Send`, like `MutexGuard` it has to unlock the `Mutex` on the thread that locked it.
+pub struct MappedMutexGuard<'a, U> {
+    raw: &'a RawMutex,
+    poison_flag: &'a poison::Flag,
+    poison: poison::Guard,
+    /// The raw pointer also opts out of `Send`
+    data: *mut U,
+    _marker: PhantomData<&'a mut U>,
+}
+
+unsafe impl<U> Sync for MappedMutexGuard<'_, U> where U: Sync {}
+
+impl<'a, U> MappedMutexGuard<'a, U> {
+    /// Associated function, takes over the lock of `guard` while giving access to `data` only.
+    fn new<T>(guard: MutexGuard<'a, T>, data: &'a mut U) -> Self {
+        // The lock is handed over to the new guard, so the old one must not unlock it
+        let guard = ManuallyDrop::new(guard);
+        Self {
+            raw: &guard.mutex.raw,
+            poison_flag: &guard.mutex.poison,
+            // Safety: the old guard is never dropped, so its `poison::Guard` is moved out only once
+            poison: unsafe { ptr::read(&guard.poison) },
+            data,
+            _marker: PhantomData,
+        }
+    }
+    /// Same as `MutexGuard::map`, narrows the guard down further.
+    pub fn map<V>(guard: Self, f: impl FnOnce(&mut U) -> &mut V) -> MappedMutexGuard<'a, V> {
+        // Safety: if we have a `MappedMutexGuard` we know we have exclusive access to the data for as long as it is locked
+        let data = f(unsafe { &mut *guard.data });
+        MappedMutexGuard::narrow(guard, data)
+    }
+    /// Same as `MutexGuard::try_map`, narrows the guard down further if `f` finds the part of the data.
+    pub fn try_map<V>(guard: Self, f: impl FnOnce(&mut U) -> Option<&mut V>) -> Result<MappedMutexGuard<'a, V>, Self> {
+        // Safety: if we have a `MappedMutexGuard` we know we have exclusive access to the data for as long as it is locked
+        match f(unsafe { &mut *guard.data }) {
+            Some(data) => Ok(MappedMutexGuard::narrow(guard, data)),
+            None => Err(guard),
+        }
+    }
+    /// Associated function, takes over the lock of `guard` while giving access to `data` only.
+    fn narrow<V>(guard: Self, data: &'a mut V) -> MappedMutexGuard<'a, V> {
+        // The lock is handed over to the new guard, so the old one must not unlock it
+        let guard = ManuallyDrop::new(guard);
+        MappedMutexGuard {
+            raw: guard.raw,
+            poison_flag: guard.poison_flag,
+            // Safety: the old guard is never dropped, so its `poison::Guard` is moved out only once
+            poison: unsafe { ptr::read(&guard.poison) },
+            data,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<U> Deref for MappedMutexGuard<'_, U> {
+    type Target = U;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have a `MappedMutexGuard` we know we have exclusive access to the data
+        unsafe { &*self.data }
+    }
+}
+
+impl<U> DerefMut for MappedMutexGuard<'_, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: if we have a `MappedMutexGuard` we know we have exclusive access to the data
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<U: fmt::Debug> fmt::Debug for MappedMutexGuard<'_, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<U> Drop for MappedMutexGuard<'_, U> {
+    fn drop(&mut self) {
+        // Poison the `Mutex` if we are unwinding from a panic, the data may be left half updated
+        self.poison_flag.done(&self.poison);
+        // Safety: if we have a `MappedMutexGuard` we know we hold the lock
+        unsafe { self.raw.unlock() };
+    }
+}
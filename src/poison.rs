@@ -0,0 +1,58 @@
+//! Lock poisoning shared by the guards of `Mutex` and `RwLock`, compatible with `std::sync::PoisonError`.
+
+use std::sync::{LockResult, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::thread;
+
+
+/// Records whether a thread panicked while holding a lock.
+pub(crate) struct Flag {
+    failed: AtomicBool,
+}
+
+impl Flag {
+    pub(crate) const fn new() -> Self {
+        Self {
+            failed: AtomicBool::new(false),
+        }
+    }
+    /// Called when the lock is taken. The returned `Guard` is passed back to `done` once it is released,
+    /// wrapped in a `PoisonError` if the lock is poisoned.
+    pub(crate) fn guard(&self) -> LockResult<Guard> {
+        let guard = Guard {
+            panicking: thread::panicking(),
+        };
+        if self.get() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+    /// Called when the lock is released, poisons it if the thread started panicking while holding it.
+    pub(crate) fn done(&self, guard: &Guard) {
+        if !guard.panicking && thread::panicking() {
+            self.failed.store(true, Relaxed);
+        }
+    }
+    pub(crate) fn get(&self) -> bool {
+        // Relaxed is enough, the lock itself orders the accesses to the data
+        self.failed.load(Relaxed)
+    }
+    pub(crate) fn clear(&self) {
+        self.failed.store(false, Relaxed);
+    }
+}
+
+/// Whether the thread holding a lock was already panicking when it took it,
+/// a lock taken during unwinding is not poisoned by that same panic.
+pub(crate) struct Guard {
+    panicking: bool,
+}
+
+/// Applies `f` to the value of `result`, keeping it wrapped in a `PoisonError` if it was.
+pub(crate) fn map_result<T, U>(result: LockResult<T>, f: impl FnOnce(T) -> U) -> LockResult<U> {
+    match result {
+        Ok(t) => Ok(f(t)),
+        Err(error) => Err(PoisonError::new(f(error.into_inner()))),
+    }
+}
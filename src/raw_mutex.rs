@@ -0,0 +1,138 @@
+//! The lock behind `Mutex`, without any data attached to it.
+
+use atomic_wait::{wait, wake_one};
+use std::hint;
+use std::sync::atomic::{AtomicU32, Ordering::{Relaxed, Acquire, Release}};
+use std::time::Instant;
+
+use crate::futex;
+
+
+const UNLOCKED: u32 = 0;
+/// Locked, and no other thread is parked waiting for the lock
+const LOCKED: u32 = 1;
+/// Locked, and other threads may be parked waiting for the lock
+const CONTENDED: u32 = 2;
+/// Unlocked, but reserved for a thread that already waited for it, handed over by `unlock_fair`
+const HANDOFF: u32 = 3;
+
+/// Upper bound for the number of spins before parking
+const MAX_SPINS: u32 = 100;
+
+/// A three state futex lock. Unlocking only issues a wake up syscall when the lock was contended.
+///
+/// A fourth state lets `unlock_fair` hand the lock to a waiting thread without unlocking it in between,
+/// only threads that already parked can take it from there.
+pub(crate) struct RawMutex {
+    state: AtomicU32,
+    /// Running average of the spins it took to get the lock in the contended path,
+    /// so mutexes protecting short critical sections spin longer than those that end up parking anyway
+    spins: AtomicU32,
+}
+
+impl RawMutex {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+            spins: AtomicU32::new(0),
+        }
+    }
+    /// Locks, blocking the current thread until the lock is available.
+    pub(crate) fn lock(&self) {
+        if !self.try_lock() {
+            self.lock_contended(None);
+        }
+    }
+    /// Locks if the lock is available, without blocking. Returns `true` if the lock was taken.
+    pub(crate) fn try_lock(&self) -> bool {
+        self.state.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed).is_ok()
+    }
+    /// Same as `lock`, but gives up once `deadline` is reached. Returns `true` if the lock was taken.
+    pub(crate) fn lock_until(&self, deadline: Instant) -> bool {
+        self.try_lock() || self.lock_contended(Some(deadline))
+    }
+    /// Unlocks, waking up a parked thread if there may be any.
+    ///
+    /// Safety: the lock must be held by the caller
+    pub(crate) unsafe fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Release) == CONTENDED {
+            wake_one(&self.state);
+        }
+    }
+    /// Unlocks like `unlock`, but if there are threads waiting the lock is handed over to them
+    /// rather than letting a thread that did not wait yet barge in.
+    ///
+    /// Safety: the lock must be held by the caller
+    pub(crate) unsafe fn unlock_fair(&self) {
+        if self.state.compare_exchange(LOCKED, UNLOCKED, Release, Relaxed).is_ok() {
+            return;
+        }
+        self.state.store(HANDOFF, Release);
+        // Threads giving up on a timed lock leave the state contended, in which case nobody may be left
+        // to take the lock over, so it is unlocked after all. Platforms that can't tell always end up here,
+        // where the woken thread competes for the lock like after `unlock`. A thread that had not parked yet
+        // may have started waiting on the handed over state in between, so it is woken to see the lock unlocked
+        if !futex::wake_one(&self.state)
+            && self.state.compare_exchange(HANDOFF, UNLOCKED, Release, Relaxed).is_ok() {
+            wake_one(&self.state);
+        }
+    }
+    /// Helper method, the slow path of `lock`. Spins for a while, then parks until the lock is available.
+    #[cold]
+    fn lock_contended(&self, deadline: Option<Instant>) -> bool {
+        if self.spin() {
+            return true;
+        }
+        // Marking the lock as contended before parking ensures the thread unlocking it issues a wake up.
+        // Taking the lock marks it as contended as well, as we can't tell whether other threads are still parked
+        let mut state = self.state.load(Relaxed);
+        // A lock handed over by `unlock_fair` is reserved for threads that already parked
+        let mut parked = false;
+        loop {
+            if state == UNLOCKED || state == LOCKED || (state == HANDOFF && parked) {
+                match self.state.compare_exchange(state, CONTENDED, Acquire, Relaxed) {
+                    Ok(UNLOCKED | HANDOFF) => return true,
+                    Ok(_) => state = CONTENDED,
+                    Err(current) => {
+                        state = current;
+                        continue;
+                    }
+                }
+            }
+            match deadline {
+                Some(deadline) => {
+                    if !futex::wait_until(&self.state, state, deadline) {
+                        return false;
+                    }
+                }
+                None => wait(&self.state, state),
+            }
+            parked = true;
+            state = self.state.load(Relaxed);
+        }
+    }
+    /// Helper method, spins up to twice the running average of spins it took to get the lock.
+    /// Returns `true` if the lock was taken.
+    fn spin(&self) -> bool {
+        let estimate = self.spins.load(Relaxed);
+        let limit = estimate.saturating_mul(2).saturating_add(10).min(MAX_SPINS);
+        let mut spins = 0;
+        let locked = loop {
+            // Only spin while nobody is parked, a parked thread means the critical section is a long one
+            let state = self.state.load(Relaxed);
+            if state == UNLOCKED && self.try_lock() {
+                break true;
+            }
+            if state >= CONTENDED || spins == limit {
+                break false;
+            }
+            spins += 1;
+            hint::spin_loop();
+        };
+        // Move the average an eighth of the way towards this run, failing to get the lock counts as the limit
+        let sample = if locked { spins } else { limit };
+        let average = estimate as i64 + (sample as i64 - estimate as i64) / 8;
+        self.spins.store(average as u32, Relaxed);
+        locked
+    }
+}
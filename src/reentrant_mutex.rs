@@ -0,0 +1,119 @@
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+use crate::raw_mutex::RawMutex;
+
+
+/// A mutex the thread holding it can lock again without deadlocking, for code that may end up calling
+/// back into itself while holding the lock.
+///
+/// Several guards of the same thread can be alive at once, so the guards only give shared access to the data.
+/// Use a `RefCell` or `Cell` inside the mutex to modify it.
+pub struct ReentrantMutex<T> {
+    raw: RawMutex,
+    /// Id of the thread holding the lock as returned by `current_thread_id`, or 0 if it is unlocked
+    owner: AtomicUsize,
+    /// How many guards the owner holds. Only accessed by the owner
+    lock_count: Cell<u32>,
+    data: T,
+}
+
+impl<T> ReentrantMutex<T> {
+    /// Associated method for creating a new `ReentrantMutex`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            raw: RawMutex::new(),
+            owner: AtomicUsize::new(0),
+            lock_count: Cell::new(0),
+            data: value,
+        }
+    }
+    /// Method for locking the mutex. If another thread holds the lock the current thread blocks until it
+    /// is unlocked, if the current thread already holds it the lock is taken again right away.
+    ///
+    /// Panics: if the current thread locks the mutex more than `u32::MAX` times
+    pub fn lock(&self) -> ReentrantMutexGuard<'_, T> {
+        if !self.lock_again() {
+            self.raw.lock();
+            self.owned();
+        }
+        ReentrantMutexGuard { mutex: self, _not_send: PhantomData }
+    }
+    /// Non-blocking version of `lock`. Returns `None` immediately if another thread holds the lock.
+    ///
+    /// Panics: if the current thread locks the mutex more than `u32::MAX` times
+    pub fn try_lock(&self) -> Option<ReentrantMutexGuard<'_, T>> {
+        if !self.lock_again() {
+            if !self.raw.try_lock() {
+                return None;
+            }
+            self.owned();
+        }
+        Some(ReentrantMutexGuard { mutex: self, _not_send: PhantomData })
+    }
+    /// Helper method, takes the lock once more if the current thread already holds it.
+    fn lock_again(&self) -> bool {
+        // Relaxed is enough, only the current thread can have stored its own id
+        if self.owner.load(Relaxed) != current_thread_id() {
+            return false;
+        }
+        let count = self.lock_count.get().checked_add(1).expect("lock count overflow in reentrant mutex");
+        self.lock_count.set(count);
+        true
+    }
+    /// Helper method, records the current thread as the owner once it took the lock.
+    fn owned(&self) {
+        self.owner.store(current_thread_id(), Relaxed);
+        self.lock_count.set(1);
+    }
+}
+
+// Only one thread at a time can access the data, like `Mutex` only `T: Send` is required
+unsafe impl<T> Sync for ReentrantMutex<T> where T: Send {}
+unsafe impl<T> Send for ReentrantMutex<T> where T: Send {}
+
+/// Returns an id unique to the current thread among the running threads, never 0.
+fn current_thread_id() -> usize {
+    thread_local!(static ID: u8 = const { 0 });
+    // The address of a thread local is unique to the thread for as long as it runs
+    ID.with(|id| id as *const u8 as usize)
+}
+
+/// A guard for `ReentrantMutex<T>`, giving shared access to its data.
+///
+/// Like `MutexGuard` the guard is not `Send`, it has to unlock the `ReentrantMutex` on the thread that locked it.
+pub struct ReentrantMutexGuard<'a, T> {
+    mutex: &'a ReentrantMutex<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T> Sync for ReentrantMutexGuard<'_, T> where T: Sync {}
+
+impl<T> Deref for ReentrantMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.mutex.data
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ReentrantMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for ReentrantMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        let count = self.mutex.lock_count.get() - 1;
+        self.mutex.lock_count.set(count);
+        // Unlock once the last guard of the owner is dropped
+        if count == 0 {
+            self.mutex.owner.store(0, Relaxed);
+            // Safety: if we have a `ReentrantMutexGuard` we know our thread holds the lock
+            unsafe { self.mutex.raw.unlock() };
+        }
+    }
+}
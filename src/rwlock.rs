@@ -0,0 +1,368 @@
+use atomic_wait::{wait, wake_all, wake_one};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::{LockResult, PoisonError, TryLockError, TryLockResult};
+use std::sync::atomic::{AtomicU32, Ordering::{Relaxed, Acquire, Release}};
+
+use crate::poison;
+
+
+/// Set while a writer is waiting for the readers to leave, keeps new readers out
+const WRITER_WAITING: u32 = 1;
+/// Set while an upgradable reader holds the lock, keeps other upgradable readers out
+const UPGRADABLE: u32 = 2;
+/// Every reader holding the lock adds one unit to the state, upgradable readers included
+const READER: u32 = 4;
+/// The state of a write locked `RwLock`, all bits are set so that it keeps out readers of any kind
+const WRITE_LOCKED: u32 = u32::MAX;
+
+/// A reader-writer lock, allowing either any number of readers or a single writer at a time.
+///
+/// The lock prefers writers: once a writer is waiting, new readers block until it has had its turn,
+/// so a steady stream of readers can not starve the writers.
+///
+/// One of the readers may hold an upgradable read lock, which can be turned into the write lock without
+/// letting any writer in between. The write lock in turn can be downgraded to a read lock.
+///
+/// Like `Mutex` the lock is poisoned if a thread panics while holding the write lock.
+pub struct RwLock<T> {
+    /// The number of readers times `READER`, plus `UPGRADABLE` if one of them is upgradable and
+    /// `WRITER_WAITING` if a writer is waiting, or `WRITE_LOCKED`
+    state: AtomicU32,
+    /// Incremented every time a writer may be able to take the lock, writers wait on this rather than on
+    /// `state` so that readers coming and going don't wake them up. An upgrading reader waits on it as well
+    writer_wake_counter: AtomicU32,
+    poison: poison::Flag,
+    data: UnsafeCell<T>,
+}
+
+impl<T> RwLock<T> {
+    /// Associated method for creating a new `RwLock`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: AtomicU32::new(0),
+            writer_wake_counter: AtomicU32::new(0),
+            poison: poison::Flag::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+    /// Method for locking the lock for reading. Blocks the current thread while the lock is write locked,
+    /// or while a writer is waiting for it.
+    ///
+    /// Errors: if the lock is poisoned, the lock is still held and the error holds the guard
+    pub fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        while !self.try_add_reader(&mut state, READER, WRITER_WAITING) {
+            wait(&self.state, state);
+            state = self.state.load(Relaxed);
+        }
+        RwLockReadGuard::new(self)
+    }
+    /// Method for locking the lock for reading, with the option to upgrade to the write lock later on.
+    /// Blocks the current thread while the lock is write locked, a writer is waiting for it, or another
+    /// thread holds an upgradable read lock. Plain readers are not blocked by an upgradable reader.
+    ///
+    /// Errors: if the lock is poisoned, the lock is still held and the error holds the guard
+    pub fn upgradable_read(&self) -> LockResult<RwLockUpgradableReadGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        while !self.try_add_reader(&mut state, READER | UPGRADABLE, WRITER_WAITING | UPGRADABLE) {
+            wait(&self.state, state);
+            state = self.state.load(Relaxed);
+        }
+        RwLockUpgradableReadGuard::new(self)
+    }
+    /// Method for locking the lock for writing. Blocks the current thread until all readers and any other
+    /// writer have released the lock.
+    ///
+    /// Errors: if the lock is poisoned, the lock is still held and the error holds the guard
+    pub fn write(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            // Take the lock if there are no readers, whether or not another writer is waiting
+            if state <= WRITER_WAITING {
+                match self.state.compare_exchange(state, WRITE_LOCKED, Acquire, Relaxed) {
+                    Ok(_) => return RwLockWriteGuard::new(self),
+                    Err(current) => {
+                        state = current;
+                        continue;
+                    }
+                }
+            }
+            // Keep new readers out while we wait
+            if state & WRITER_WAITING == 0 {
+                if let Err(current) = self.state.compare_exchange(state, state | WRITER_WAITING, Relaxed, Relaxed) {
+                    state = current;
+                    continue;
+                }
+            }
+            // Load the counter before re-checking the state, so a release in between is never missed
+            let counter = self.writer_wake_counter.load(Acquire);
+            state = self.state.load(Relaxed);
+            if state > WRITER_WAITING {
+                wait(&self.writer_wake_counter, counter);
+                state = self.state.load(Relaxed);
+            }
+        }
+    }
+    /// Non-blocking version of `read`. Fails with `TryLockError::WouldBlock` immediately if the lock is
+    /// write locked or a writer is waiting, or with `TryLockError::Poisoned` if it was locked but is poisoned.
+    pub fn try_read(&self) -> TryLockResult<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        if self.try_add_reader(&mut state, READER, WRITER_WAITING) {
+            Ok(RwLockReadGuard::new(self)?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+    /// Non-blocking version of `upgradable_read`. Fails with `TryLockError::WouldBlock` immediately if the lock
+    /// is write locked, a writer is waiting or another thread holds an upgradable read lock, or with
+    /// `TryLockError::Poisoned` if it was locked but is poisoned.
+    pub fn try_upgradable_read(&self) -> TryLockResult<RwLockUpgradableReadGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        if self.try_add_reader(&mut state, READER | UPGRADABLE, WRITER_WAITING | UPGRADABLE) {
+            Ok(RwLockUpgradableReadGuard::new(self)?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+    /// Non-blocking version of `write`. Fails with `TryLockError::WouldBlock` immediately if the lock is
+    /// held by anyone, or with `TryLockError::Poisoned` if it was locked but is poisoned.
+    pub fn try_write(&self) -> TryLockResult<RwLockWriteGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        while state <= WRITER_WAITING {
+            match self.state.compare_exchange(state, WRITE_LOCKED, Acquire, Relaxed) {
+                Ok(_) => return Ok(RwLockWriteGuard::new(self)?),
+                Err(current) => state = current,
+            }
+        }
+        Err(TryLockError::WouldBlock)
+    }
+    /// Returns `true` if a thread panicked while holding the write lock.
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+    /// Clears the poisoned state of the lock, for when the data was recovered and is known to be valid again.
+    pub fn clear_poison(&self) {
+        self.poison.clear();
+    }
+    /// Helper method, adds `units` to the `state` last seen, as long as none of the bits in `blocked_by` are set.
+    /// Returns `true` if the reader was added, otherwise `state` holds the state that blocked it.
+    fn try_add_reader(&self, state: &mut u32, units: u32, blocked_by: u32) -> bool {
+        while *state & blocked_by == 0 {
+            assert!(*state < WRITE_LOCKED - units, "too many readers");
+            match self.state.compare_exchange_weak(*state, *state + units, Acquire, Relaxed) {
+                Ok(_) => return true,
+                Err(current) => *state = current,
+            }
+        }
+        false
+    }
+    /// Helper method, wakes up a writer, or with `all` set every writer along with an upgrading reader.
+    fn wake_writers(&self, all: bool) {
+        self.writer_wake_counter.fetch_add(1, Release);
+        if all {
+            wake_all(&self.writer_wake_counter);
+        } else {
+            wake_one(&self.writer_wake_counter);
+        }
+    }
+}
+
+// Like `std::sync::RwLock`, readers on different threads share the data, so it has to be `Sync` as well
+unsafe impl<T> Sync for RwLock<T> where T: Send + Sync {}
+unsafe impl<T> Send for RwLock<T> where T: Send {}
+
+/// A guard giving shared access to the data of a read locked `RwLock<T>`.
+///
+/// Like `MutexGuard` the guard is not `Send`, it has to unlock the `RwLock` on the thread that locked it.
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T> Sync for RwLockReadGuard<'_, T> where T: Sync {}
+
+impl<'a, T> RwLockReadGuard<'a, T> {
+    /// Associated function, wraps a read locked `lock` in a guard, failing if it is poisoned.
+    fn new(lock: &'a RwLock<T>) -> LockResult<Self> {
+        // Readers can't modify the data, so they never poison the lock themselves
+        poison::map_result(lock.poison.guard(), |_| RwLockReadGuard { lock, _not_send: PhantomData })
+    }
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have a `RwLockReadGuard` we know nobody has mutable access to the data
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        // The last reader leaving while a writer is waiting wakes up that writer. The last plain reader leaving
+        // the upgradable reader behind may have to wake it up, but it waits among the writers and only waking
+        // one of them could miss it
+        match self.lock.state.fetch_sub(READER, Release) - READER {
+            WRITER_WAITING => self.lock.wake_writers(false),
+            state if state == READER | UPGRADABLE | WRITER_WAITING => self.lock.wake_writers(true),
+            _ => (),
+        }
+    }
+}
+
+/// A guard giving shared access to the data of a `RwLock<T>`, which can be upgraded to a `RwLockWriteGuard`.
+/// Only one thread at a time can hold an upgradable read lock, plain readers can still share it.
+///
+/// Like `MutexGuard` the guard is not `Send`, it has to unlock the `RwLock` on the thread that locked it.
+pub struct RwLockUpgradableReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T> Sync for RwLockUpgradableReadGuard<'_, T> where T: Sync {}
+
+impl<'a, T> RwLockUpgradableReadGuard<'a, T> {
+    /// Associated function, wraps an upgradable read locked `lock` in a guard, failing if it is poisoned.
+    fn new(lock: &'a RwLock<T>) -> LockResult<Self> {
+        poison::map_result(lock.poison.guard(), |_| RwLockUpgradableReadGuard { lock, _not_send: PhantomData })
+    }
+    /// Turns the upgradable read lock into the write lock, blocking the current thread until the other readers
+    /// have left. No writer can take the lock in between, so the data read is still current once upgraded.
+    /// Readers arriving while the upgrade waits are blocked, like they are for any waiting writer.
+    pub fn upgrade(guard: Self) -> RwLockWriteGuard<'a, T> {
+        let lock = guard.lock;
+        // The read lock is not released, it is turned into the write lock
+        mem::forget(guard);
+        let mut state = lock.state.load(Relaxed);
+        loop {
+            // Take the write lock once we are the only reader left
+            if state & !WRITER_WAITING == READER | UPGRADABLE {
+                match lock.state.compare_exchange(state, WRITE_LOCKED, Acquire, Relaxed) {
+                    Ok(_) => break,
+                    Err(current) => {
+                        state = current;
+                        continue;
+                    }
+                }
+            }
+            // Keep new readers out while we wait
+            if state & WRITER_WAITING == 0 {
+                if let Err(current) = lock.state.compare_exchange(state, state | WRITER_WAITING, Relaxed, Relaxed) {
+                    state = current;
+                    continue;
+                }
+            }
+            // Load the counter before re-checking the state, so the last reader leaving is never missed
+            let counter = lock.writer_wake_counter.load(Acquire);
+            state = lock.state.load(Relaxed);
+            if state & !WRITER_WAITING != READER | UPGRADABLE {
+                wait(&lock.writer_wake_counter, counter);
+                state = lock.state.load(Relaxed);
+            }
+        }
+        // Only writers poison the lock and none could have taken it while we held the upgradable read lock,
+        // so a poisoned lock was already reported when it was read locked
+        let poison = lock.poison.guard().unwrap_or_else(PoisonError::into_inner);
+        RwLockWriteGuard { lock, poison, _not_send: PhantomData }
+    }
+}
+
+impl<T> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have a `RwLockUpgradableReadGuard` we know nobody has mutable access to the data
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.lock.state.fetch_sub(READER | UPGRADABLE, Release) - (READER | UPGRADABLE) == WRITER_WAITING {
+            self.lock.wake_writers(false);
+        }
+        // Threads waiting for the upgradable read lock wait on the state
+        wake_all(&self.lock.state);
+    }
+}
+
+/// A guard giving exclusive access to the data of a write locked `RwLock<T>`.
+///
+/// Like `MutexGuard` the guard is not `Send`, it has to unlock the `RwLock` on the thread that locked it.
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    poison: poison::Guard,
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T> Sync for RwLockWriteGuard<'_, T> where T: Sync {}
+
+impl<'a, T> RwLockWriteGuard<'a, T> {
+    /// Associated function, wraps a write locked `lock` in a guard, failing if it is poisoned.
+    fn new(lock: &'a RwLock<T>) -> LockResult<Self> {
+        poison::map_result(lock.poison.guard(), |poison| RwLockWriteGuard { lock, poison, _not_send: PhantomData })
+    }
+    /// Turns the write lock into a read lock, without letting any other writer in between.
+    /// Waiting readers are let in along with it.
+    pub fn downgrade(guard: Self) -> RwLockReadGuard<'a, T> {
+        let lock = guard.lock;
+        // Poison the `RwLock` if we are unwinding from a panic, the data may be left half updated
+        lock.poison.done(&guard.poison);
+        // The write lock is not released, it is turned into a read lock
+        mem::forget(guard);
+        lock.state.store(READER, Release);
+        // Waiting writers saw the lock as write locked and did not set `WRITER_WAITING`,
+        // they have to be woken up to set it again, or the last reader leaving won't wake them
+        lock.wake_writers(true);
+        wake_all(&lock.state);
+        RwLockReadGuard { lock, _not_send: PhantomData }
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have a `RwLockWriteGuard` we know we have exclusive access to the data
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: if we have a `RwLockWriteGuard` we know we have exclusive access to the data
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        // Poison the `RwLock` if we are unwinding from a panic, the data may be left half updated
+        self.lock.poison.done(&self.poison);
+        // Clearing the state also clears `WRITER_WAITING`, waiting writers set it again once they are woken
+        self.lock.state.store(0, Release);
+        // Wake up a waiting writer as well as all waiting readers, whoever is first takes the lock
+        self.lock.wake_writers(false);
+        wake_all(&self.lock.state);
+    }
+}
@@ -0,0 +1,584 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, Ordering::{Relaxed, SeqCst}};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::wait_queue::{Lists, QueueGuard, WaitList, WaitQueue, Waiter};
+
+
+/// A basic Semaphore implementation. Keeps track of a counter which can have configurable max and initial values.
+/// Can be used to implement other synchronization primitives.
+///
+/// Threads which cannot move the counter right away are parked in a queue. By default a thread calling `wait`
+/// or `signal` may still overtake the queued threads if the counter allows it, see `Semaphore::fair` for
+/// a `Semaphore` serving threads strictly in arrival order.
+pub struct Semaphore {
+    counter: AtomicU32,
+    /// Only changed while holding the lock of `queue`
+    max: AtomicU32,
+    /// Whether queued threads are served strictly in arrival order
+    fair: bool,
+    /// Set once by `close`, after which every operation fails
+    closed: AtomicBool,
+    /// Number of threads queued in `wait`, waiting for the counter to be large enough
+    acquirers: AtomicU32,
+    /// Number of threads queued in `signal`, waiting for the counter to be small enough
+    releasers: AtomicU32,
+    queue: WaitQueue,
+    /// Set by the non-blocking path when it moved the counter but could not lock the queue to grant the requests
+    /// this allows, the thread holding the lock grants them before unlocking
+    needs_drain: AtomicBool,
+}
+
+/// The two kinds of threads which can be queued on a `Semaphore`.
+#[derive(Clone, Copy)]
+enum Side {
+    /// Threads waiting to decrease the counter
+    Acquirers,
+    /// Threads waiting to increase the counter
+    Releasers,
+}
+
+impl Semaphore {
+    /// Associated function, initializes `self.max` to `u32::MAX` and `self.counter` to 0.
+    pub const fn new() -> Self {
+        Self::init(0, u32::MAX)
+    }
+    /// Method for configuring the initial value and max value of the `Semaphore`
+    ///
+    /// Panics: if `max` < `count`, at compile time when used to initialize a `static` or `const`
+    pub const fn init(count: u32, max: u32) -> Self {
+        assert!(count <= max, "count cannot be greater than max");
+        Self {
+            counter: AtomicU32::new(count),
+            max: AtomicU32::new(max),
+            fair: false,
+            closed: AtomicBool::new(false),
+            acquirers: AtomicU32::new(0),
+            releasers: AtomicU32::new(0),
+            queue: WaitQueue::new(),
+            needs_drain: AtomicBool::new(false),
+        }
+    }
+    /// Same as `init`, but the `Semaphore` is fair: blocked threads move the counter strictly in the order
+    /// they arrived in, and no thread can overtake them, not even with the non-blocking methods.
+    /// A thread waiting to move the counter by more than 1 holds up everyone queued behind it until it can.
+    ///
+    /// Panics: if `max` < `count`, at compile time when used to initialize a `static` or `const`
+    pub const fn fair(count: u32, max: u32) -> Self {
+        let mut semaphore = Self::init(count, max);
+        semaphore.fair = true;
+        semaphore
+    }
+    /// Increases the counter by 1 if possible. If the counter is strictly less than the maximum set
+    /// then the method will increase the count, otherwise the method will block the current threads
+    /// execution, waiting for the counter to be less than the maximum.
+    ///
+    /// Errors: if the `Semaphore` is closed, before or while blocking
+    pub fn signal(&self) -> Result<(), Closed> {
+        self.signal_deadline(1, None).map(drop)
+    }
+    /// Same as `signal`, but gives up once `timeout` has elapsed.
+    /// Returns `Ok(true)` if the counter was increased, `Ok(false)` if the timeout elapsed first.
+    pub fn signal_timeout(&self, timeout: Duration) -> Result<bool, Closed> {
+        self.signal_deadline(1, Instant::now().checked_add(timeout))
+    }
+    /// Same as `signal`, but gives up once `deadline` is reached.
+    /// Returns `Ok(true)` if the counter was increased, `Ok(false)` if the deadline was reached first.
+    pub fn signal_until(&self, deadline: Instant) -> Result<bool, Closed> {
+        self.signal_deadline(1, Some(deadline))
+    }
+    /// Increases the counter by `n` in a single step. Blocks the current threads execution until the counter
+    /// can be increased by `n` without exceeding the maximum. If `n` is greater than the maximum the call
+    /// only returns once the maximum is raised with `set_max` or `add_capacity`.
+    ///
+    /// Errors: if the `Semaphore` is closed, before or while blocking
+    pub fn signal_n(&self, n: u32) -> Result<(), Closed> {
+        self.signal_deadline(n, None).map(drop)
+    }
+    /// Attempts to decrease the counter by 1 if possible. If the counter is equal to zero, then
+    /// the method will block the current threads execution, waiting for the counter to be greater than zero.
+    ///
+    /// Errors: if the `Semaphore` is closed, before or while blocking
+    pub fn wait(&self) -> Result<(), Closed> {
+        self.wait_deadline(1, None).map(drop)
+    }
+    /// Same as `wait`, but gives up once `timeout` has elapsed.
+    /// Returns `Ok(true)` if the counter was decreased, `Ok(false)` if the timeout elapsed first.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<bool, Closed> {
+        self.wait_deadline(1, Instant::now().checked_add(timeout))
+    }
+    /// Same as `wait`, but gives up once `deadline` is reached.
+    /// Returns `Ok(true)` if the counter was decreased, `Ok(false)` if the deadline was reached first.
+    pub fn wait_until(&self, deadline: Instant) -> Result<bool, Closed> {
+        self.wait_deadline(1, Some(deadline))
+    }
+    /// Decreases the counter by `n` in a single step. Blocks the current threads execution until the counter
+    /// is at least `n`. Either all `n` are taken or none are, so two threads waiting for part of
+    /// the count can never deadlock by each holding half of it. If `n` is greater than the maximum the call
+    /// only returns once the maximum is raised with `set_max` or `add_capacity`.
+    ///
+    /// Errors: if the `Semaphore` is closed, before or while blocking
+    pub fn wait_n(&self, n: u32) -> Result<(), Closed> {
+        self.wait_deadline(n, None).map(drop)
+    }
+    /// Decreases the counter by 1 like `wait`, returning a `SemaphorePermit` which increases it again once dropped.
+    /// Prefer this over pairing `wait` and `signal` by hand, the permit is released on early returns and panics too.
+    pub fn acquire(&self) -> Result<SemaphorePermit<'_>, Closed> {
+        self.wait()?;
+        Ok(SemaphorePermit { semaphore: self })
+    }
+    /// Same as `acquire`, but the returned `OwnedSemaphorePermit` keeps the `Semaphore` alive through an `Arc`
+    /// instead of borrowing it, so it can be moved into spawned threads or stored in other structs.
+    pub fn acquire_owned(self: Arc<Self>) -> Result<OwnedSemaphorePermit, Closed> {
+        self.wait()?;
+        Ok(OwnedSemaphorePermit { semaphore: self })
+    }
+    /// Same as `acquire`, but instead of blocking the current thread the returned future registers the `Waker`
+    /// of the task polling it, so it can be awaited on any executor.
+    ///
+    /// The future is cancellation safe: dropping it gives up its place in the queue, or gives the unit back
+    /// if it was already taken on its behalf.
+    pub fn acquire_async(&self) -> AcquireFuture<'_> {
+        AcquireFuture {
+            semaphore: self,
+            waiter: Waiter::new(1),
+            queued: false,
+        }
+    }
+    /// Non-blocking version of `signal`. Increases the counter by 1 if it is strictly less than the maximum
+    /// and returns `true`, otherwise returns `false` immediately without blocking the current thread.
+    /// Always returns `false` once the `Semaphore` is closed.
+    ///
+    /// The current thread never parks, not even on the internal lock of the queue. If that lock is taken,
+    /// the queued threads the new count allows to proceed are woken by the thread holding it. Otherwise they
+    /// are woken right away, which calls the `Waker` of queued tasks on the current thread.
+    pub fn try_signal(&self) -> bool {
+        self.try_signal_n(1)
+    }
+    /// Non-blocking version of `signal_n`. Increases the counter by `n` if it would not exceed the maximum
+    /// and returns `true`, otherwise returns `false` immediately without blocking the current thread.
+    /// Never parks the current thread, see `try_signal`.
+    pub fn try_signal_n(&self, n: u32) -> bool {
+        self.try_move(Side::Releasers, n)
+    }
+    /// Non-blocking version of `wait`. Decreases the counter by 1 if it is greater than zero and returns `true`,
+    /// otherwise returns `false` immediately without blocking the current thread.
+    /// Always returns `false` once the `Semaphore` is closed. Never parks the current thread, see `try_signal`.
+    pub fn try_wait(&self) -> bool {
+        self.try_wait_n(1)
+    }
+    /// Non-blocking version of `wait_n`. Decreases the counter by `n` if it is at least `n` and returns `true`,
+    /// otherwise returns `false` immediately without blocking the current thread.
+    /// Never parks the current thread, see `try_signal`.
+    pub fn try_wait_n(&self, n: u32) -> bool {
+        self.try_move(Side::Acquirers, n)
+    }
+    /// Closes the `Semaphore`. Every thread blocked in `wait` or `signal` is woken up with a `Closed` error,
+    /// and every later call fails immediately. The counter is left as it was. Closing cannot be undone.
+    pub fn close(&self) {
+        let mut lists = self.lock_queue();
+        // Set under the lock, so no thread can queue itself after the lists were closed
+        self.closed.store(true, SeqCst);
+        let acquirers = lists.acquirers.close();
+        self.acquirers.fetch_sub(acquirers, Relaxed);
+        let releasers = lists.releasers.close();
+        self.releasers.fetch_sub(releasers, Relaxed);
+    }
+    /// Returns `true` if `close` has been called.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(SeqCst)
+    }
+    /// Returns the current value of the counter, the number of units that can be taken with `wait` right now.
+    /// The value may already be outdated by the time it is used, so it is only suitable for monitoring.
+    pub fn available(&self) -> u32 {
+        self.counter.load(Relaxed)
+    }
+    /// Returns the maximum value of the counter.
+    pub fn max(&self) -> u32 {
+        self.max.load(Relaxed)
+    }
+    /// Changes the maximum value of the counter, waking up threads blocked in `signal` if it was raised.
+    /// The maximum may be lowered below the current value of the counter, in which case `signal` blocks
+    /// until enough units were taken with `wait` to bring the counter below the new maximum.
+    /// Permits dropped while the counter is at the new maximum give up their unit rather than blocking.
+    pub fn set_max(&self, max: u32) {
+        self.update_max(|_| max);
+    }
+    /// Raises the maximum value of the counter by `n`, saturating at `u32::MAX`. See `set_max`.
+    pub fn add_capacity(&self, n: u32) {
+        self.update_max(|max| max.saturating_add(n));
+    }
+    /// Lowers the maximum value of the counter by `n`, saturating at 0. See `set_max`.
+    pub fn shrink_capacity(&self, n: u32) {
+        self.update_max(|max| max.saturating_sub(n));
+    }
+    /// Returns the approximate number of threads blocked in `wait`.
+    pub fn waiting_acquirers(&self) -> u32 {
+        self.acquirers.load(Relaxed)
+    }
+    /// Returns the approximate number of threads blocked in `signal`.
+    pub fn waiting_releasers(&self) -> u32 {
+        self.releasers.load(Relaxed)
+    }
+    /// Helper method, replaces the maximum with `update` applied to it and grants the requests it allows.
+    fn update_max(&self, update: impl FnOnce(u32) -> u32) {
+        let mut lists = self.lock_queue();
+        // Changed under the lock, so a thread queueing itself in `block` re-checks the counter against
+        // either the old maximum before it is drained below, or the new one
+        self.max.store(update(self.max.load(Relaxed)), Relaxed);
+        self.drain(&mut lists);
+    }
+    /// Helper method implementing the blocking variants of `signal`, a `deadline` of `None` blocks indefinitely.
+    fn signal_deadline(&self, n: u32, deadline: Option<Instant>) -> Result<bool, Closed> {
+        if self.try_move(Side::Releasers, n) {
+            return Ok(true);
+        }
+        self.block(Side::Releasers, n, deadline)
+    }
+    /// Helper method implementing the blocking variants of `wait`, a `deadline` of `None` blocks indefinitely.
+    fn wait_deadline(&self, n: u32, deadline: Option<Instant>) -> Result<bool, Closed> {
+        if self.try_move(Side::Acquirers, n) {
+            return Ok(true);
+        }
+        self.block(Side::Acquirers, n, deadline)
+    }
+    /// Helper method, the fast path of every operation. Moves the counter by `n` for `side` without blocking,
+    /// unless the `Semaphore` is closed, or is fair and other threads of the same side are already queued.
+    /// Only ever tries to lock the queue once, so it never parks the current thread.
+    fn try_move(&self, side: Side, n: u32) -> bool {
+        if self.is_closed() || self.fair && self.waiters(side).load(SeqCst) > 0 {
+            return false;
+        }
+        if !self.try_update(side, n) {
+            return false;
+        }
+        self.moved(side);
+        true
+    }
+    /// Helper method, gives back the unit of a dropped permit without blocking. If the counter is already at
+    /// the maximum, which happens when the maximum was lowered while permits were out, the unit is dropped
+    /// instead, so the lowered maximum is absorbed as the permits come back.
+    ///
+    /// Unlike `signal` the unit is given back even if threads are queued in `signal` of a fair `Semaphore`,
+    /// the permit returns a unit it took rather than adding a new one.
+    fn release_permit(&self) {
+        // Nobody is waiting for the unit of a closed `Semaphore` anymore
+        if !self.is_closed() && self.try_update(Side::Releasers, 1) {
+            self.moved(Side::Releasers);
+        }
+    }
+    /// Helper method, called once the counter was moved for `side` outside of the queue. Queued threads of the
+    /// other side may be able to make progress now, they are granted their requests without parking the
+    /// current thread.
+    fn moved(&self, side: Side) {
+        let other = match side {
+            Side::Acquirers => Side::Releasers,
+            Side::Releasers => Side::Acquirers,
+        };
+        if self.waiters(other).load(SeqCst) > 0 {
+            // Locking the queue could park the current thread, if it is busy whoever holds the lock drains it instead
+            self.needs_drain.store(true, SeqCst);
+            fence(SeqCst);
+            if let Some(lists) = self.queue.try_lock() {
+                drop(LockedQueue::new(self, lists));
+            }
+        }
+    }
+    /// Helper method, the slow path of every operation. Queues the current thread until the counter
+    /// was moved by `n` on its behalf, `deadline` is reached or the `Semaphore` is closed.
+    fn block(&self, side: Side, n: u32, deadline: Option<Instant>) -> Result<bool, Closed> {
+        let waiter = Waiter::new(n);
+        // Safety: `waiter` is not moved, and is removed from the queue before it goes out of scope,
+        // either by a thread granting its request, by `close` or below once the deadline is reached
+        if let Some(result) = unsafe { self.enqueue(side, &waiter, None) } {
+            return result.map(|()| true);
+        }
+        if !waiter.park(deadline) && self.dequeue(side, &waiter) {
+            return Ok(false);
+        }
+        if waiter.is_granted() {
+            Ok(true)
+        } else {
+            Err(Closed)
+        }
+    }
+    /// Helper method, moves the counter by the request of `waiter` right away if possible, otherwise links
+    /// `waiter` into the queue of `side`, to be woken through `waker` if given or by unparking the thread.
+    ///
+    /// Returns `None` if `waiter` was queued.
+    ///
+    /// Safety: a queued `waiter` must not be moved or dropped before it is removed from the queue again
+    unsafe fn enqueue(&self, side: Side, waiter: &Waiter, waker: Option<&Waker>) -> Option<Result<(), Closed>> {
+        let mut lists = self.lock_queue();
+        if self.is_closed() {
+            return Some(Err(Closed));
+        }
+        // Registering before re-checking the counter ensures that any thread changing the counter after this
+        // point observes a non-zero waiter count and drains the queue, so no wake up can be lost.
+        self.waiters(side).fetch_add(1, SeqCst);
+        let overtakes = self.fair && !Self::list(&mut lists, side).is_empty();
+        if !overtakes && self.try_update(side, waiter.n()) {
+            self.waiters(side).fetch_sub(1, Relaxed);
+            self.drain(&mut lists);
+            return Some(Ok(()));
+        }
+        if let Some(waker) = waker {
+            waiter.set_waker(waker);
+        }
+        Self::list(&mut lists, side).push_back(waiter);
+        None
+    }
+    /// Helper method, removes a `waiter` queued on `side` whose request is no longer wanted.
+    ///
+    /// Returns `false` if `waiter` was already removed, because its request was granted or the `Semaphore` closed.
+    fn dequeue(&self, side: Side, waiter: &Waiter) -> bool {
+        let mut lists = self.lock_queue();
+        // Only checked under the lock, `waiter` may be removed by another thread up until it is taken
+        if !waiter.is_waiting() {
+            return false;
+        }
+        // Safety: a `Waiter` which is still waiting is linked into the queue it was enqueued in
+        unsafe { Self::list(&mut lists, side).remove(waiter) };
+        self.waiters(side).fetch_sub(1, Relaxed);
+        // With `waiter` gone the threads queued behind it may be able to make progress
+        self.drain(&mut lists);
+        true
+    }
+    /// Helper method, grants the requests of queued threads for as long as the counter allows it.
+    /// Must be called with the queue locked.
+    fn drain(&self, lists: &mut Lists) {
+        loop {
+            // Granting one side moves the counter, which may allow the other side to make progress
+            let acquired = lists.acquirers.grant(self.fair, |n| self.try_update(Side::Acquirers, n));
+            self.acquirers.fetch_sub(acquired, Relaxed);
+            let released = lists.releasers.grant(self.fair, |n| self.try_update(Side::Releasers, n));
+            self.releasers.fetch_sub(released, Relaxed);
+            if acquired == 0 && released == 0 {
+                return;
+            }
+        }
+    }
+    /// Helper method, attempts to move the counter by `n` for `side`, decreasing it for acquirers
+    /// and increasing it for releasers, respecting the bounds of the counter.
+    fn try_update(&self, side: Side, n: u32) -> bool {
+        // SeqCst pairs with the SeqCst increment of the waiter counts in `enqueue`
+        let update = |count: u32| match side {
+            Side::Acquirers => count.checked_sub(n),
+            Side::Releasers => count.checked_add(n).filter(|&count| count <= self.max.load(Relaxed)),
+        };
+        self.counter.fetch_update(SeqCst, SeqCst, update).is_ok()
+    }
+    /// Helper method, locks the queue. Requests left for the lock holder by the non-blocking path are granted
+    /// when the returned guard is dropped.
+    fn lock_queue(&self) -> LockedQueue<'_> {
+        LockedQueue::new(self, self.queue.lock())
+    }
+    /// Helper method, the number of threads queued on `side`.
+    fn waiters(&self, side: Side) -> &AtomicU32 {
+        match side {
+            Side::Acquirers => &self.acquirers,
+            Side::Releasers => &self.releasers,
+        }
+    }
+    /// Helper method, the list of threads queued on `side`.
+    fn list(lists: &mut Lists, side: Side) -> &mut WaitList {
+        match side {
+            Side::Acquirers => &mut lists.acquirers,
+            Side::Releasers => &mut lists.releasers,
+        }
+    }
+}
+
+/// The locked queue of a `Semaphore`, which grants the requests left by the non-blocking path before unlocking.
+struct LockedQueue<'a> {
+    semaphore: &'a Semaphore,
+    lists: ManuallyDrop<QueueGuard<'a>>,
+}
+
+impl<'a> LockedQueue<'a> {
+    fn new(semaphore: &'a Semaphore, lists: QueueGuard<'a>) -> Self {
+        Self { semaphore, lists: ManuallyDrop::new(lists) }
+    }
+}
+
+impl Deref for LockedQueue<'_> {
+    type Target = Lists;
+    fn deref(&self) -> &Self::Target {
+        &self.lists
+    }
+}
+
+impl DerefMut for LockedQueue<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.lists
+    }
+}
+
+impl Drop for LockedQueue<'_> {
+    fn drop(&mut self) {
+        let semaphore = self.semaphore;
+        loop {
+            if semaphore.needs_drain.swap(false, SeqCst) {
+                semaphore.drain(&mut self.lists);
+            }
+            // Safety: the guard is either replaced below or never used again
+            unsafe { ManuallyDrop::drop(&mut self.lists) };
+            // A request left after the check above, while the queue was still locked, has to be granted here.
+            // Pairs with the fence in `try_move`: either we see the flag or it sees the queue unlocked.
+            fence(SeqCst);
+            if !semaphore.needs_drain.load(SeqCst) {
+                return;
+            }
+            match semaphore.queue.try_lock() {
+                Some(lists) => self.lists = ManuallyDrop::new(lists),
+                // The new holder of the lock grants them
+                None => return,
+            }
+        }
+    }
+}
+
+/// A guard for a unit of a `Semaphore`'s counter taken by `Semaphore::acquire`.
+/// Gives the unit back when dropped, without ever blocking. If the maximum was lowered in the meantime and
+/// the counter is already at the new maximum, the unit is dropped instead.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl SemaphorePermit<'_> {
+    /// Consumes the permit without giving its unit back to the `Semaphore`,
+    /// permanently decreasing the counter by 1.
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        // Give the unit taken in `Semaphore::acquire` back, waking up a waiting thread
+        self.semaphore.release_permit();
+    }
+}
+
+/// The future returned by `Semaphore::acquire_async`, resolving to a `SemaphorePermit` once a unit was taken.
+pub struct AcquireFuture<'a> {
+    semaphore: &'a Semaphore,
+    waiter: Waiter,
+    /// Whether `waiter` was linked into the queue, it has to be removed again before the future is dropped
+    queued: bool,
+}
+
+impl<'a> Future for AcquireFuture<'a> {
+    type Output = Result<SemaphorePermit<'a>, Closed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: nothing is moved out of the future, `waiter` in particular has to stay in place while queued
+        let this = unsafe { self.get_unchecked_mut() };
+        let semaphore = this.semaphore;
+        if !this.queued {
+            if semaphore.try_move(Side::Acquirers, 1) {
+                return Poll::Ready(Ok(SemaphorePermit { semaphore }));
+            }
+            // Safety: the future is pinned, and removes `waiter` from the queue when dropped
+            return match unsafe { semaphore.enqueue(Side::Acquirers, &this.waiter, Some(cx.waker())) } {
+                Some(result) => Poll::Ready(result.map(|()| SemaphorePermit { semaphore })),
+                None => {
+                    this.queued = true;
+                    Poll::Pending
+                }
+            };
+        }
+        if this.waiter.is_waiting() {
+            let _lists = semaphore.lock_queue();
+            // Re-checked under the lock, `waiter` may have been removed in the meantime
+            if this.waiter.is_waiting() {
+                // The future may have moved to another task since it was last polled
+                // Safety: the queue is locked
+                unsafe { this.waiter.set_waker(cx.waker()) };
+                return Poll::Pending;
+            }
+        }
+        this.queued = false;
+        if this.waiter.is_granted() {
+            Poll::Ready(Ok(SemaphorePermit { semaphore }))
+        } else {
+            Poll::Ready(Err(Closed))
+        }
+    }
+}
+
+impl Drop for AcquireFuture<'_> {
+    fn drop(&mut self) {
+        if self.queued
+            && !self.semaphore.dequeue(Side::Acquirers, &self.waiter)
+            && self.waiter.is_granted() {
+            // The unit was taken on behalf of the future, but never handed out as a permit
+            drop(SemaphorePermit { semaphore: self.semaphore });
+        }
+    }
+}
+
+/// An owned version of `SemaphorePermit` returned by `Semaphore::acquire_owned`.
+/// Gives the unit back when dropped like `SemaphorePermit`, without ever blocking.
+pub struct OwnedSemaphorePermit {
+    semaphore: Arc<Semaphore>,
+}
+
+impl OwnedSemaphorePermit {
+    /// Returns the `Semaphore` the permit was acquired from.
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+    /// Consumes the permit without giving its unit back to the `Semaphore`,
+    /// permanently decreasing the counter by 1. The reference to the `Semaphore` is still released.
+    pub fn forget(self) {
+        let permit = ManuallyDrop::new(self);
+        // Safety: `permit` is never used again and its destructor never runs, so the `Arc` is moved out exactly once
+        drop(unsafe { std::ptr::read(&permit.semaphore) });
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        // Give the unit taken in `Semaphore::acquire_owned` back, waking up a waiting thread
+        self.semaphore.release_permit();
+    }
+}
+
+/// The error returned by the blocking operations of a `Semaphore` once it has been closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("semaphore closed")
+    }
+}
+
+impl Error for Closed {}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("available", &self.available())
+            .field("max", &self.max())
+            .field("fair", &self.fair)
+            .field("closed", &self.is_closed())
+            .field("waiting_acquirers", &self.waiting_acquirers())
+            .field("waiting_releasers", &self.waiting_releasers())
+            .finish()
+    }
+}
+
+impl Default for Semaphore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,282 @@
+//! Intrusive queue of parked threads and tasks used by `Semaphore` to hand out its counter in arrival order.
+//!
+//! Each `Waiter` lives on the stack of the thread waiting on it, or inside the pinned future of the task
+//! waiting on it, the queue only links them together.
+
+use atomic_wait::{wait, wake_one};
+use std::cell::{Cell, UnsafeCell};
+use std::marker::PhantomPinned;
+use std::ops::{Deref, DerefMut};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering::{Relaxed, Acquire, Release}};
+use std::task::Waker;
+use std::time::Instant;
+
+use crate::futex;
+
+
+/// The `Waiter` is linked into a queue and its thread is parked
+const WAITING: u32 = 0;
+/// The `Waiter` was removed from its queue and its request was fulfilled
+const GRANTED: u32 = 1;
+/// The `Waiter` was removed from its queue because the `Semaphore` was closed
+const CLOSED: u32 = 2;
+
+/// A thread or task waiting to move a `Semaphore`'s counter by `n`.
+pub(crate) struct Waiter {
+    n: u32,
+    state: AtomicU32,
+    /// Set for tasks, which are woken through the `Waker` instead of `state`. Only accessed under the queue's lock
+    waker: UnsafeCell<Option<Waker>>,
+    prev: Cell<*const Waiter>,
+    next: Cell<*const Waiter>,
+    // The queue holds pointers to the `Waiter`, it must not move while linked
+    _pinned: PhantomPinned,
+}
+
+impl Waiter {
+    pub(crate) fn new(n: u32) -> Self {
+        Self {
+            n,
+            state: AtomicU32::new(WAITING),
+            waker: UnsafeCell::new(None),
+            prev: Cell::new(ptr::null()),
+            next: Cell::new(ptr::null()),
+            _pinned: PhantomPinned,
+        }
+    }
+    /// The amount the `Waiter` wants to move the counter by.
+    pub(crate) fn n(&self) -> u32 {
+        self.n
+    }
+    /// Returns `true` while the `Waiter` is linked into a queue.
+    pub(crate) fn is_waiting(&self) -> bool {
+        self.state.load(Acquire) == WAITING
+    }
+    /// Returns `true` once the `Waiter` was granted its request.
+    pub(crate) fn is_granted(&self) -> bool {
+        self.state.load(Acquire) == GRANTED
+    }
+    /// Registers `waker` to be woken instead of the thread once the `Waiter` is removed from its queue.
+    ///
+    /// Safety: must be called while holding the lock of the queue the `Waiter` is or will be linked into.
+    pub(crate) unsafe fn set_waker(&self, waker: &Waker) {
+        let current = &mut *self.waker.get();
+        if !current.as_ref().is_some_and(|current| current.will_wake(waker)) {
+            *current = Some(waker.clone());
+        }
+    }
+    /// Blocks the current thread until the `Waiter` is removed from its queue or `deadline` is reached.
+    ///
+    /// Returns `true` if the `Waiter` was removed. On `false` the `Waiter` may still be queued, or may have been
+    /// removed right after the deadline, so the caller must re-check under the queue's lock.
+    pub(crate) fn park(&self, deadline: Option<Instant>) -> bool {
+        while self.state.load(Acquire) == WAITING {
+            match deadline {
+                Some(deadline) => {
+                    if !futex::wait_until(&self.state, WAITING, deadline) {
+                        return false;
+                    }
+                }
+                None => wait(&self.state, WAITING),
+            }
+        }
+        true
+    }
+}
+
+// Safety: the links and the `Waker` are only accessed while holding the lock of the queue
+unsafe impl Send for Waiter {}
+unsafe impl Sync for Waiter {}
+
+/// A doubly linked list of `Waiter`s, in arrival order.
+pub(crate) struct WaitList {
+    head: *const Waiter,
+    tail: *const Waiter,
+    /// `Waker`s of removed `Waiter`s, woken once the queue is unlocked so they never run under the lock
+    wakers: Vec<Waker>,
+}
+
+impl WaitList {
+    const fn new() -> Self {
+        Self {
+            head: ptr::null(),
+            tail: ptr::null(),
+            wakers: Vec::new(),
+        }
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.head.is_null()
+    }
+    /// Links `waiter` at the back of the list.
+    ///
+    /// Safety: `waiter` must not be linked already, and must stay alive and in place until it is removed.
+    pub(crate) unsafe fn push_back(&mut self, waiter: &Waiter) {
+        waiter.prev.set(self.tail);
+        waiter.next.set(ptr::null());
+        match self.tail.as_ref() {
+            Some(tail) => tail.next.set(waiter),
+            None => self.head = waiter,
+        }
+        self.tail = waiter;
+    }
+    /// Unlinks `waiter` from the list.
+    ///
+    /// Safety: `waiter` must be linked into this list.
+    pub(crate) unsafe fn remove(&mut self, waiter: &Waiter) {
+        let (prev, next) = (waiter.prev.get(), waiter.next.get());
+        match prev.as_ref() {
+            Some(prev) => prev.next.set(next),
+            None => self.head = next,
+        }
+        match next.as_ref() {
+            Some(next) => next.prev.set(prev),
+            None => self.tail = prev,
+        }
+    }
+    /// Walks the list from the front, removing and waking every `Waiter` for which `grant` returns `true`.
+    /// With `in_order` set the walk stops at the first `Waiter` that is not granted, so nobody can overtake it.
+    ///
+    /// Returns the number of `Waiter`s granted.
+    pub(crate) fn grant(&mut self, in_order: bool, mut grant: impl FnMut(u32) -> bool) -> u32 {
+        let mut granted = 0;
+        let mut cursor = self.head;
+        // Safety: linked `Waiter`s stay alive until they are removed, which only happens under the queue's lock
+        while let Some(waiter) = unsafe { cursor.as_ref() } {
+            let current = cursor;
+            cursor = waiter.next.get();
+            if grant(waiter.n) {
+                unsafe {
+                    self.remove(waiter);
+                    self.wake(current, GRANTED);
+                }
+                granted += 1;
+            } else if in_order {
+                break;
+            }
+        }
+        granted
+    }
+    /// Removes and wakes every `Waiter` in the list without granting their requests.
+    ///
+    /// Returns the number of `Waiter`s removed.
+    pub(crate) fn close(&mut self) -> u32 {
+        let mut closed = 0;
+        let mut cursor = self.head;
+        // Safety: linked `Waiter`s stay alive until they are removed, which only happens under the queue's lock
+        while let Some(waiter) = unsafe { cursor.as_ref() } {
+            let current = cursor;
+            cursor = waiter.next.get();
+            unsafe { self.wake(current, CLOSED) };
+            closed += 1;
+        }
+        self.head = ptr::null();
+        self.tail = ptr::null();
+        closed
+    }
+    /// Marks an unlinked `waiter` with `state` and wakes its thread.
+    ///
+    /// Safety: `waiter` must still be waiting, and the list must not access it afterwards.
+    unsafe fn wake(&mut self, waiter: *const Waiter, state: u32) {
+        let waker = (*(*waiter).waker.get()).take();
+        let waiter_state = ptr::addr_of!((*waiter).state);
+        (*waiter_state).store(state, Release);
+        // The waiting thread may return and free `waiter` as soon as it observes the store,
+        // so only the address is used from here on, which `wake_one` allows.
+        match waker {
+            Some(waker) => self.wakers.push(waker),
+            None => wake_one(waiter_state),
+        }
+    }
+}
+
+/// The waiting lists of both sides of a `Semaphore`.
+pub(crate) struct Lists {
+    /// Threads waiting to decrease the counter
+    pub(crate) acquirers: WaitList,
+    /// Threads waiting to increase the counter
+    pub(crate) releasers: WaitList,
+}
+
+/// A queue of `Waiter`s protected by a small three state lock (unlocked, locked, locked with waiters).
+pub(crate) struct WaitQueue {
+    state: AtomicU32,
+    lists: UnsafeCell<Lists>,
+}
+
+impl WaitQueue {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(0),
+            lists: UnsafeCell::new(Lists {
+                acquirers: WaitList::new(),
+                releasers: WaitList::new(),
+            }),
+        }
+    }
+    /// Locks the queue, blocking the current thread until it is available.
+    pub(crate) fn lock(&self) -> QueueGuard<'_> {
+        if self.state.compare_exchange(0, 1, Acquire, Relaxed).is_err() {
+            // The critical sections are short, spin for a little while before parking
+            for _ in 0..100 {
+                if self.state.load(Relaxed) == 0
+                    && self.state.compare_exchange(0, 1, Acquire, Relaxed).is_ok() {
+                    return QueueGuard { queue: self };
+                }
+                std::hint::spin_loop();
+            }
+            while self.state.swap(2, Acquire) != 0 {
+                wait(&self.state, 2);
+            }
+        }
+        QueueGuard { queue: self }
+    }
+    /// Locks the queue if it is available, with a single attempt that never spins or parks.
+    pub(crate) fn try_lock(&self) -> Option<QueueGuard<'_>> {
+        self.state.compare_exchange(0, 1, Acquire, Relaxed).is_ok().then(|| QueueGuard { queue: self })
+    }
+}
+
+// Safety: the raw pointers in `Lists` are only accessed while holding the lock
+unsafe impl Send for WaitQueue {}
+unsafe impl Sync for WaitQueue {}
+
+// No user code runs while the lock is held, so a panic can never leave the lists in an inconsistent state
+impl UnwindSafe for WaitQueue {}
+impl RefUnwindSafe for WaitQueue {}
+
+/// A guard giving exclusive access to the `Lists` of a locked `WaitQueue`.
+pub(crate) struct QueueGuard<'a> {
+    queue: &'a WaitQueue,
+}
+
+impl Deref for QueueGuard<'_> {
+    type Target = Lists;
+    fn deref(&self) -> &Self::Target {
+        // Safety: if we have a `QueueGuard` we know we have exclusive access to the lists
+        unsafe { &*self.queue.lists.get() }
+    }
+}
+
+impl DerefMut for QueueGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: if we have a `QueueGuard` we know we have exclusive access to the lists
+        unsafe { &mut *self.queue.lists.get() }
+    }
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        let acquirers = mem::take(&mut self.acquirers.wakers);
+        let releasers = mem::take(&mut self.releasers.wakers);
+        if self.queue.state.swap(0, Release) == 2 {
+            wake_one(&self.queue.state);
+        }
+        // A `Waker` may run arbitrary code, including code using the `Semaphore` again
+        for waker in acquirers.into_iter().chain(releasers) {
+            waker.wake();
+        }
+    }
+}
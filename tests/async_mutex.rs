@@ -0,0 +1,66 @@
+use semaphore_rust::executor::block_on;
+use semaphore_rust::AsyncMutex;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+
+/// A future which is pending once, waking itself, so the task yields to the executor.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[test]
+fn guard_is_held_across_await() {
+    let mutex = AsyncMutex::new(0);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                block_on(async {
+                    for _ in 0..100 {
+                        let mut guard = mutex.lock().await;
+                        let value = *guard;
+                        YieldNow(false).await;
+                        *guard = value + 1;
+                    }
+                })
+            });
+        }
+    });
+    assert_eq!(*block_on(mutex.lock()), 400);
+}
+
+#[test]
+fn dropped_lock_future_passes_the_lock_on() {
+    let mutex = AsyncMutex::new(());
+    let mut cx = Context::from_waker(Waker::noop());
+    let guard = block_on(mutex.lock());
+    let mut first = Box::pin(mutex.lock());
+    let mut second = pin!(mutex.lock());
+    assert!(first.as_mut().poll(&mut cx).is_pending());
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+    // Unlocking hands the lock to `first`, which is dropped without ever being polled again
+    drop(guard);
+    drop(first);
+    assert!(second.as_mut().poll(&mut cx).is_ready());
+}
+
+#[test]
+fn lock_future_and_guard_are_send() {
+    fn assert_send<T: Send>(_: &T) {}
+    let mutex = AsyncMutex::new(Vec::<u32>::new());
+    let lock = mutex.lock();
+    assert_send(&lock);
+    assert_send(&block_on(lock));
+}
@@ -0,0 +1,90 @@
+use semaphore_rust::executor::block_on;
+use semaphore_rust::{Closed, Semaphore};
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn acquire_async_is_ready_when_available() {
+    let semaphore = Semaphore::init(1, 1);
+    let permit = block_on(semaphore.acquire_async()).unwrap();
+    assert_eq!(semaphore.available(), 0);
+    drop(permit);
+    assert_eq!(semaphore.available(), 1);
+}
+
+#[test]
+fn acquire_async_is_woken_by_signal() {
+    let semaphore = Semaphore::new();
+    thread::scope(|s| {
+        let task = s.spawn(|| block_on(async { semaphore.acquire_async().await.map(|permit| permit.forget()) }));
+        while semaphore.waiting_acquirers() == 0 {
+            thread::yield_now();
+        }
+        semaphore.signal().unwrap();
+        task.join().unwrap().unwrap();
+    });
+    assert_eq!(semaphore.available(), 0);
+}
+
+#[test]
+fn dropped_pending_acquire_leaves_the_queue() {
+    let semaphore = Semaphore::new();
+    let mut cx = Context::from_waker(Waker::noop());
+    {
+        let mut acquire = pin!(semaphore.acquire_async());
+        assert!(acquire.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(semaphore.waiting_acquirers(), 1);
+    }
+    assert_eq!(semaphore.waiting_acquirers(), 0);
+    semaphore.signal().unwrap();
+    assert_eq!(semaphore.available(), 1);
+}
+
+#[test]
+fn dropped_granted_acquire_gives_the_unit_back() {
+    let semaphore = Semaphore::new();
+    let mut cx = Context::from_waker(Waker::noop());
+    {
+        let mut acquire = pin!(semaphore.acquire_async());
+        assert!(acquire.as_mut().poll(&mut cx).is_pending());
+        // The unit is handed to the queued future, which is dropped before it is polled again
+        semaphore.signal().unwrap();
+        assert_eq!(semaphore.available(), 0);
+    }
+    assert_eq!(semaphore.available(), 1);
+}
+
+#[test]
+fn fair_acquire_async_is_served_in_order() {
+    let semaphore = Semaphore::fair(0, 2);
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut first = pin!(semaphore.acquire_async());
+    let mut second = pin!(semaphore.acquire_async());
+    assert!(first.as_mut().poll(&mut cx).is_pending());
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+    semaphore.signal().unwrap();
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(first.as_mut().poll(&mut cx), Poll::Ready(Ok(_))));
+}
+
+#[test]
+fn close_wakes_acquire_async() {
+    let semaphore = Semaphore::new();
+    thread::scope(|s| {
+        let task = s.spawn(|| block_on(semaphore.acquire_async()).err());
+        thread::sleep(Duration::from_millis(50));
+        semaphore.close();
+        assert_eq!(task.join().unwrap(), Some(Closed));
+    });
+}
+
+#[test]
+fn acquire_async_is_send() {
+    fn assert_send<T: Send>(_: &T) {}
+    let semaphore = Semaphore::new();
+    assert_send(&semaphore.acquire_async());
+}
@@ -0,0 +1,88 @@
+use semaphore_rust::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+#[test]
+fn wait_is_woken_by_notify_one() {
+    let mutex = Mutex::new(false);
+    let condvar = Condvar::new();
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            *mutex.lock().unwrap() = true;
+            condvar.notify_one();
+        });
+        let mut ready = mutex.lock().unwrap();
+        while !*ready {
+            ready = condvar.wait(ready).unwrap();
+        }
+    });
+}
+
+#[test]
+fn wait_while_wakes_all_waiters() {
+    let mutex = Mutex::new(0);
+    let condvar = Condvar::new();
+    thread::scope(|s| {
+        let waiters: Vec<_> = (0..4).map(|_| s.spawn(|| {
+            let value = condvar.wait_while(mutex.lock().unwrap(), |value| *value == 0).unwrap();
+            *value
+        })).collect();
+        thread::sleep(Duration::from_millis(50));
+        *mutex.lock().unwrap() = 7;
+        condvar.notify_all();
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), 7);
+        }
+    });
+}
+
+#[test]
+fn wait_timeout_times_out() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+    let start = Instant::now();
+    let (guard, result) = condvar.wait_timeout(mutex.lock().unwrap(), Duration::from_millis(50)).unwrap();
+    assert!(result.timed_out());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    // The mutex is locked again once the wait returns
+    assert!(mutex.try_lock().is_err());
+    drop(guard);
+}
+
+#[test]
+fn wait_timeout_is_notified() {
+    let mutex = Mutex::new(false);
+    let condvar = Condvar::new();
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(20));
+            *mutex.lock().unwrap() = true;
+            condvar.notify_one();
+        });
+        let mut ready = mutex.lock().unwrap();
+        while !*ready {
+            let (guard, result) = condvar.wait_timeout(ready, Duration::from_secs(10)).unwrap();
+            assert!(!result.timed_out());
+            ready = guard;
+        }
+    });
+}
+
+#[test]
+fn wait_reports_poisoned_mutex() {
+    let mutex = Mutex::new(false);
+    let condvar = Condvar::new();
+    thread::scope(|s| {
+        let poisoner = s.spawn(|| {
+            thread::sleep(Duration::from_millis(20));
+            let _guard = mutex.lock().unwrap();
+            condvar.notify_one();
+            panic!("poisoning the mutex");
+        });
+        let result = condvar.wait_while(mutex.lock().unwrap(), |_| !mutex.is_poisoned());
+        assert!(result.is_err());
+        assert!(poisoner.join().is_err());
+    });
+}
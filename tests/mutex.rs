@@ -0,0 +1,299 @@
+use semaphore_rust::{MappedMutexGuard, Mutex, MutexGuard, Semaphore};
+use std::cell::Cell;
+use std::sync::TryLockError;
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+#[test]
+fn lock_is_exclusive() {
+    let mutex = Mutex::new(0);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    *mutex.lock().unwrap() += 1;
+                }
+            });
+        }
+    });
+    assert_eq!(*mutex.lock().unwrap(), 4000);
+}
+
+#[test]
+fn parked_lockers_are_woken() {
+    let mutex = Mutex::new(0);
+    thread::scope(|s| {
+        let guard = mutex.lock().unwrap();
+        for _ in 0..4 {
+            s.spawn(|| *mutex.lock().unwrap() += 1);
+        }
+        // Long enough for the lockers to give up spinning and park
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+    });
+    assert_eq!(*mutex.lock().unwrap(), 4);
+}
+
+#[test]
+fn try_lock_fails_while_locked() {
+    let mutex = Mutex::new(());
+    let guard = mutex.try_lock().unwrap();
+    assert!(matches!(mutex.try_lock(), Err(TryLockError::WouldBlock)));
+    drop(guard);
+    assert!(mutex.try_lock().is_ok());
+}
+
+#[test]
+fn try_lock_for_gives_up() {
+    let mutex = Mutex::new(());
+    let _guard = mutex.lock().unwrap();
+    let start = Instant::now();
+    assert!(matches!(mutex.try_lock_for(Duration::from_millis(50)), Err(TryLockError::WouldBlock)));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(mutex.try_lock_until(Instant::now()).is_err());
+}
+
+#[test]
+fn try_lock_for_succeeds_once_unlocked() {
+    let mutex = Mutex::new(());
+    thread::scope(|s| {
+        let guard = mutex.lock().unwrap();
+        let waiter = s.spawn(|| mutex.try_lock_for(Duration::from_secs(10)).is_ok());
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+        assert!(waiter.join().unwrap());
+    });
+}
+
+/// Panics while holding the lock of `mutex`, after setting its value to 1.
+fn poison(mutex: &Mutex<i32>) {
+    thread::scope(|s| {
+        let result = s.spawn(|| {
+            let mut guard = mutex.lock().unwrap();
+            *guard = 1;
+            panic!("poisoning the mutex");
+        }).join();
+        assert!(result.is_err());
+    });
+}
+
+#[test]
+fn panic_while_locked_poisons() {
+    let mutex = Mutex::new(0);
+    assert!(!mutex.is_poisoned());
+    poison(&mutex);
+    assert!(mutex.is_poisoned());
+    let error = mutex.lock().unwrap_err();
+    // The data is still reachable through the error
+    assert_eq!(*error.into_inner(), 1);
+    assert!(matches!(mutex.try_lock(), Err(TryLockError::Poisoned(_))));
+    assert!(matches!(mutex.try_lock_for(Duration::from_millis(10)), Err(TryLockError::Poisoned(_))));
+}
+
+#[test]
+fn clear_poison_recovers() {
+    let mutex = Mutex::new(0);
+    poison(&mutex);
+    *mutex.lock().unwrap_or_else(|error| error.into_inner()) = 0;
+    mutex.clear_poison();
+    assert!(!mutex.is_poisoned());
+    assert_eq!(*mutex.lock().unwrap(), 0);
+}
+
+#[test]
+fn lock_taken_while_unwinding_is_not_poisoned() {
+    struct LockOnDrop<'a>(&'a Mutex<i32>);
+    impl Drop for LockOnDrop<'_> {
+        fn drop(&mut self) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    let mutex = Mutex::new(0);
+    thread::scope(|s| {
+        let result = s.spawn(|| {
+            let _lock_on_drop = LockOnDrop(&mutex);
+            panic!("unwinding");
+        }).join();
+        assert!(result.is_err());
+    });
+    assert!(!mutex.is_poisoned());
+    assert_eq!(*mutex.lock().unwrap(), 1);
+}
+
+#[test]
+fn auto_traits_match_std() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_sync<T: Sync>() {}
+    // Only `T: Send` is required for the mutex to be shared, like `std::sync::Mutex`
+    assert_send_sync::<Mutex<Cell<u32>>>();
+    assert_sync::<MutexGuard<'static, u32>>();
+    assert_send_sync::<Semaphore>();
+}
+
+#[test]
+fn map_keeps_the_lock() {
+    let mutex = Mutex::new((1, vec!['a']));
+    let mut chars = MutexGuard::map(mutex.lock().unwrap(), |(_, chars)| chars);
+    chars.push('b');
+    assert!(mutex.try_lock().is_err());
+    let mut first = MappedMutexGuard::map(chars, |chars| chars.first_mut().unwrap());
+    *first = 'c';
+    drop(first);
+    assert_eq!(*mutex.lock().unwrap(), (1, vec!['c', 'b']));
+}
+
+#[test]
+fn try_map_returns_the_guard() {
+    let mutex = Mutex::new(vec![1]);
+    let guard = MutexGuard::try_map(mutex.lock().unwrap(), |values| values.get_mut(1)).unwrap_err();
+    let mut first = MutexGuard::try_map(guard, |values| values.get_mut(0)).unwrap();
+    *first += 1;
+    let first = MappedMutexGuard::try_map(first, |_| None::<&mut u8>).unwrap_err();
+    drop(first);
+    assert_eq!(*mutex.lock().unwrap(), [2]);
+}
+
+#[test]
+fn panic_while_mapped_poisons() {
+    let mutex = Mutex::new((0, 0));
+    thread::scope(|s| {
+        let result = s.spawn(|| {
+            let mut first = MutexGuard::map(mutex.lock().unwrap(), |(first, _)| first);
+            *first = 1;
+            panic!("poisoning the mutex");
+        }).join();
+        assert!(result.is_err());
+    });
+    assert!(mutex.is_poisoned());
+    assert_eq!(*mutex.lock().unwrap_err().into_inner(), (1, 0));
+}
+
+#[test]
+fn unlocked_releases_the_lock_for_the_closure() {
+    let mutex = Mutex::new(0);
+    let mut guard = mutex.lock().unwrap();
+    *guard = 1;
+    let seen = MutexGuard::unlocked(&mut guard, || {
+        let mut other = mutex.try_lock().unwrap();
+        *other += 1;
+        *other
+    });
+    assert_eq!(seen, 2);
+    assert!(mutex.try_lock().is_err());
+    *guard += 1;
+    drop(guard);
+    assert_eq!(*mutex.lock().unwrap(), 3);
+}
+
+#[test]
+fn unlocked_locks_again_on_panic() {
+    let mutex = Mutex::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut guard = mutex.lock().unwrap();
+        MutexGuard::unlocked(&mut guard, || {
+            assert!(mutex.try_lock().is_ok());
+            panic!("panicking while unlocked");
+        });
+    }));
+    assert!(result.is_err());
+    // The panic happened while the mutex was unlocked, so it is not poisoned
+    assert!(!mutex.is_poisoned());
+    assert!(mutex.try_lock().is_ok());
+}
+
+#[test]
+fn unlock_fair_hands_over_to_a_waiter() {
+    let mutex = Mutex::new(Vec::new());
+    let release = AtomicBool::new(false);
+    thread::scope(|s| {
+        let guard = mutex.lock().unwrap();
+        let waiter = s.spawn(|| {
+            let mut guard = mutex.lock().unwrap();
+            guard.push("waiter");
+            // Holding on to the lock, so it can't have been unlocked again when the main thread checks it
+            while !release.load(Relaxed) {
+                thread::yield_now();
+            }
+        });
+        // Long enough for the waiter to give up spinning and park
+        thread::sleep(Duration::from_millis(50));
+        MutexGuard::unlock_fair(guard);
+        // The lock is either reserved for the waiter or held by it, it is never available to the main thread
+        assert!(matches!(mutex.try_lock(), Err(TryLockError::WouldBlock)));
+        release.store(true, Relaxed);
+        mutex.lock().unwrap().push("main");
+        waiter.join().unwrap();
+    });
+    assert_eq!(*mutex.lock().unwrap(), ["waiter", "main"]);
+}
+
+#[test]
+fn unlock_fair_without_waiters_unlocks() {
+    let mutex = Mutex::new(());
+    MutexGuard::unlock_fair(mutex.lock().unwrap());
+    assert!(mutex.try_lock().is_ok());
+    thread::scope(|s| {
+        let guard = mutex.lock().unwrap();
+        // The only waiter gives up, leaving nobody to hand the lock over to
+        s.spawn(|| assert!(mutex.try_lock_for(Duration::from_millis(20)).is_err())).join().unwrap();
+        MutexGuard::unlock_fair(guard);
+    });
+    assert!(mutex.try_lock().is_ok());
+}
+
+#[test]
+fn unlock_fair_wakes_lockers_arriving_without_waiters() {
+    let mutex = Mutex::new(0);
+    for _ in 0..200 {
+        thread::scope(|s| {
+            let guard = mutex.lock().unwrap();
+            // The timed waiter gives up, leaving the lock contended with nobody parked
+            s.spawn(|| assert!(mutex.try_lock_for(Duration::from_millis(1)).is_err())).join().unwrap();
+            // Lockers still on their way to park while the lock is handed over to nobody
+            let lockers: Vec<_> = (0..4).map(|_| s.spawn(|| *mutex.lock().unwrap() += 1)).collect();
+            MutexGuard::unlock_fair(guard);
+            for locker in lockers {
+                locker.join().unwrap();
+            }
+        });
+    }
+    assert_eq!(*mutex.lock().unwrap(), 800);
+}
+
+#[test]
+fn into_inner_and_get_mut() {
+    let mut mutex = Mutex::from(vec![1]);
+    mutex.get_mut().unwrap().push(2);
+    assert_eq!(mutex.into_inner().unwrap(), [1, 2]);
+
+    let mut mutex = Mutex::<i32>::default();
+    poison(&mutex);
+    *mutex.get_mut().unwrap_err().into_inner() += 1;
+    assert_eq!(mutex.into_inner().unwrap_err().into_inner(), 2);
+}
+
+#[test]
+fn debug_does_not_block() {
+    let mutex = Mutex::new(1);
+    assert_eq!(format!("{mutex:?}"), "Mutex { data: 1, poisoned: false, .. }");
+    let guard = mutex.lock().unwrap();
+    assert_eq!(format!("{mutex:?}"), "Mutex { data: <locked>, poisoned: false, .. }");
+    drop(guard);
+    poison(&mutex);
+    assert_eq!(format!("{mutex:?}"), "Mutex { data: 1, poisoned: true, .. }");
+}
+
+#[test]
+fn mutex_in_a_static() {
+    static COUNTER: Mutex<u32> = Mutex::new(0);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| *COUNTER.lock().unwrap() += 1);
+        }
+    });
+    assert_eq!(*COUNTER.lock().unwrap(), 4);
+}
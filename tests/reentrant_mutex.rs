@@ -0,0 +1,55 @@
+use semaphore_rust::ReentrantMutex;
+use std::cell::RefCell;
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn same_thread_locks_again() {
+    let mutex = ReentrantMutex::new(RefCell::new(0));
+    let outer = mutex.lock();
+    let inner = mutex.lock();
+    *inner.borrow_mut() += 1;
+    assert!(mutex.try_lock().is_some());
+    drop(inner);
+    assert_eq!(*outer.borrow(), 1);
+}
+
+#[test]
+fn other_threads_wait_for_the_last_guard() {
+    let mutex = ReentrantMutex::new(RefCell::new(Vec::new()));
+    thread::scope(|s| {
+        let outer = mutex.lock();
+        let inner = mutex.lock();
+        let other = s.spawn(|| {
+            assert!(mutex.try_lock().is_none());
+            mutex.lock().borrow_mut().push("other");
+        });
+        thread::sleep(Duration::from_millis(50));
+        inner.borrow_mut().push("inner");
+        drop(inner);
+        thread::sleep(Duration::from_millis(50));
+        outer.borrow_mut().push("outer");
+        drop(outer);
+        other.join().unwrap();
+    });
+    assert_eq!(*mutex.lock().borrow(), ["inner", "outer", "other"]);
+}
+
+#[test]
+fn recursive_callbacks() {
+    fn visit(mutex: &ReentrantMutex<RefCell<u32>>, depth: u32) {
+        let guard = mutex.lock();
+        *guard.borrow_mut() += 1;
+        if depth > 0 {
+            visit(mutex, depth - 1);
+        }
+    }
+    let mutex = ReentrantMutex::new(RefCell::new(0));
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| visit(&mutex, 9));
+        }
+    });
+    assert_eq!(*mutex.lock().borrow(), 40);
+}
@@ -0,0 +1,142 @@
+use semaphore_rust::{RwLock, RwLockUpgradableReadGuard, RwLockWriteGuard};
+use std::sync::TryLockError;
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn readers_share_the_lock() {
+    let lock = RwLock::new(1);
+    let first = lock.read().unwrap();
+    let second = lock.read().unwrap();
+    assert_eq!(*first + *second, 2);
+    assert!(lock.try_read().is_ok());
+    assert!(matches!(lock.try_write(), Err(TryLockError::WouldBlock)));
+    drop((first, second));
+    assert!(lock.try_write().is_ok());
+}
+
+#[test]
+fn writer_is_exclusive() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    *lock.write().unwrap() += 1;
+                    assert!(*lock.read().unwrap() > 0);
+                }
+            });
+        }
+    });
+    assert_eq!(*lock.read().unwrap(), 4000);
+    let guard = lock.write().unwrap();
+    assert!(matches!(lock.try_read(), Err(TryLockError::WouldBlock)));
+    assert!(matches!(lock.try_write(), Err(TryLockError::WouldBlock)));
+    drop(guard);
+}
+
+#[test]
+fn waiting_writer_blocks_new_readers() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        let reader = lock.read().unwrap();
+        let writer = s.spawn(|| *lock.write().unwrap() = 1);
+        // Give the writer time to start waiting
+        thread::sleep(Duration::from_millis(50));
+        assert!(matches!(lock.try_read(), Err(TryLockError::WouldBlock)));
+        let late_reader = s.spawn(|| *lock.read().unwrap());
+        thread::sleep(Duration::from_millis(50));
+        drop(reader);
+        writer.join().unwrap();
+        // The reader arriving after the writer sees its write
+        assert_eq!(late_reader.join().unwrap(), 1);
+    });
+}
+
+#[test]
+fn panicking_writer_poisons() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        let result = s.spawn(|| {
+            let _guard = lock.write().unwrap();
+            panic!("poisoning the lock");
+        }).join();
+        assert!(result.is_err());
+    });
+    assert!(lock.is_poisoned());
+    assert!(lock.read().is_err());
+    assert!(matches!(lock.try_write(), Err(TryLockError::Poisoned(_))));
+    lock.clear_poison();
+    assert!(lock.write().is_ok());
+}
+
+#[test]
+fn panicking_reader_does_not_poison() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        let result = s.spawn(|| {
+            let _guard = lock.read().unwrap();
+            panic!("reading only");
+        }).join();
+        assert!(result.is_err());
+    });
+    assert!(!lock.is_poisoned());
+    assert!(lock.write().is_ok());
+}
+
+#[test]
+fn upgradable_reader_shares_with_readers_only() {
+    let lock = RwLock::new(0);
+    let upgradable = lock.upgradable_read().unwrap();
+    assert!(lock.try_read().is_ok());
+    assert!(matches!(lock.try_upgradable_read(), Err(TryLockError::WouldBlock)));
+    assert!(matches!(lock.try_write(), Err(TryLockError::WouldBlock)));
+    drop(upgradable);
+    assert!(lock.try_upgradable_read().is_ok());
+}
+
+#[test]
+fn upgrade_waits_for_readers() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        let reader = lock.read().unwrap();
+        let upgrader = s.spawn(|| {
+            let upgradable = lock.upgradable_read().unwrap();
+            let value = *upgradable;
+            let mut guard = RwLockUpgradableReadGuard::upgrade(upgradable);
+            *guard = value + 1;
+        });
+        // Writers queue up behind the upgrade, the value they read is only written after it
+        let writer = s.spawn(|| {
+            thread::sleep(Duration::from_millis(20));
+            *lock.write().unwrap() *= 10;
+        });
+        thread::sleep(Duration::from_millis(50));
+        // The waiting upgrade keeps new readers out
+        assert!(matches!(lock.try_read(), Err(TryLockError::WouldBlock)));
+        drop(reader);
+        upgrader.join().unwrap();
+        writer.join().unwrap();
+    });
+    assert_eq!(*lock.read().unwrap(), 10);
+}
+
+#[test]
+fn downgrade_keeps_writers_out() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        let mut guard = lock.write().unwrap();
+        let writer = s.spawn(|| *lock.write().unwrap() = 2);
+        thread::sleep(Duration::from_millis(50));
+        *guard = 1;
+        let reader = RwLockWriteGuard::downgrade(guard);
+        assert!(matches!(lock.try_write(), Err(TryLockError::WouldBlock)));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(*reader, 1);
+        // The waiting writer gets the lock once the downgraded reader leaves
+        drop(reader);
+        writer.join().unwrap();
+    });
+    assert_eq!(*lock.read().unwrap(), 2);
+}
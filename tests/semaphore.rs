@@ -0,0 +1,441 @@
+use semaphore_rust::{Closed, Mutex, Semaphore};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+#[test]
+fn wait_is_released_by_signal() {
+    let semaphore = Semaphore::new();
+    thread::scope(|s| {
+        let waiter = s.spawn(|| semaphore.wait().unwrap());
+        // Give the waiter time to park on the empty semaphore
+        thread::sleep(Duration::from_millis(50));
+        semaphore.signal().unwrap();
+        waiter.join().unwrap();
+    });
+}
+
+#[test]
+fn signal_is_released_by_wait() {
+    let semaphore = Semaphore::init(1, 1);
+    thread::scope(|s| {
+        let releaser = s.spawn(|| semaphore.signal().unwrap());
+        // Give the releaser time to park on the full semaphore
+        thread::sleep(Duration::from_millis(50));
+        semaphore.wait().unwrap();
+        releaser.join().unwrap();
+    });
+}
+
+#[test]
+fn many_waiters_are_released() {
+    let semaphore = Semaphore::init(0, 4);
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| semaphore.wait().unwrap());
+        }
+        for _ in 0..8 {
+            semaphore.signal().unwrap();
+        }
+    });
+}
+
+#[test]
+fn try_wait_and_try_signal_never_block() {
+    let semaphore = Semaphore::init(1, 2);
+    assert!(semaphore.try_wait());
+    assert!(!semaphore.try_wait());
+    assert!(semaphore.try_signal());
+    assert!(semaphore.try_signal());
+    assert!(!semaphore.try_signal());
+}
+
+#[test]
+fn try_signal_wakes_waiter() {
+    let semaphore = Semaphore::new();
+    thread::scope(|s| {
+        let waiter = s.spawn(|| semaphore.wait().unwrap());
+        thread::sleep(Duration::from_millis(50));
+        assert!(semaphore.try_signal());
+        waiter.join().unwrap();
+    });
+}
+
+#[test]
+fn wait_timeout_gives_up() {
+    let semaphore = Semaphore::new();
+    let start = Instant::now();
+    assert!(!semaphore.wait_timeout(Duration::from_millis(50)).unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(!semaphore.wait_until(Instant::now()).unwrap());
+}
+
+#[test]
+fn signal_timeout_gives_up() {
+    let semaphore = Semaphore::init(1, 1);
+    let start = Instant::now();
+    assert!(!semaphore.signal_timeout(Duration::from_millis(50)).unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn wait_timeout_is_released_by_signal() {
+    let semaphore = Semaphore::new();
+    thread::scope(|s| {
+        let waiter = s.spawn(|| semaphore.wait_timeout(Duration::from_secs(10)).unwrap());
+        thread::sleep(Duration::from_millis(50));
+        semaphore.signal().unwrap();
+        assert!(waiter.join().unwrap());
+    });
+}
+
+#[test]
+fn wait_n_takes_all_or_nothing() {
+    let semaphore = Semaphore::init(3, 10);
+    assert!(!semaphore.try_wait_n(4));
+    assert!(semaphore.try_wait_n(3));
+    assert!(semaphore.try_signal_n(10));
+    assert!(!semaphore.try_signal_n(1));
+}
+
+#[test]
+fn wait_n_is_released_by_signal_n() {
+    let semaphore = Semaphore::init(0, 8);
+    thread::scope(|s| {
+        let waiters: Vec<_> = (0..2).map(|_| s.spawn(|| semaphore.wait_n(4).unwrap())).collect();
+        thread::sleep(Duration::from_millis(50));
+        semaphore.signal_n(8).unwrap();
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+    });
+    assert!(!semaphore.try_wait());
+}
+
+#[test]
+fn competing_wait_n_do_not_deadlock() {
+    let semaphore = Semaphore::init(4, 4);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    semaphore.wait_n(3).unwrap();
+                    semaphore.signal_n(3).unwrap();
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn signal_n_is_released_by_wait() {
+    let semaphore = Semaphore::init(3, 4);
+    thread::scope(|s| {
+        let releaser = s.spawn(|| semaphore.signal_n(3).unwrap());
+        thread::sleep(Duration::from_millis(50));
+        semaphore.wait_n(2).unwrap();
+        releaser.join().unwrap();
+    });
+    assert!(semaphore.try_wait_n(4));
+}
+
+#[test]
+fn permit_is_released_on_drop() {
+    let semaphore = Semaphore::init(1, 1);
+    let permit = semaphore.acquire().unwrap();
+    assert!(!semaphore.try_wait());
+    drop(permit);
+    assert!(semaphore.try_wait());
+}
+
+#[test]
+fn permit_is_released_on_panic() {
+    let semaphore = Semaphore::init(1, 1);
+    let result = std::panic::catch_unwind(|| {
+        let _permit = semaphore.acquire().unwrap();
+        panic!("released while unwinding");
+    });
+    assert!(result.is_err());
+    assert!(semaphore.try_wait());
+}
+
+#[test]
+fn forgotten_permit_is_not_released() {
+    let semaphore = Semaphore::init(1, 1);
+    semaphore.acquire().unwrap().forget();
+    assert!(!semaphore.try_wait());
+}
+
+#[test]
+fn owned_permit_moves_into_spawned_thread() {
+    let semaphore = Arc::new(Semaphore::init(2, 2));
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let permit = Arc::clone(&semaphore).acquire_owned().unwrap();
+            thread::spawn(move || drop(permit))
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(semaphore.try_wait_n(2));
+}
+
+#[test]
+fn forgotten_owned_permit_releases_the_arc() {
+    let semaphore = Arc::new(Semaphore::init(1, 1));
+    Arc::clone(&semaphore).acquire_owned().unwrap().forget();
+    assert_eq!(Arc::strong_count(&semaphore), 1);
+    assert!(!semaphore.try_wait());
+}
+
+#[test]
+fn fair_serves_in_arrival_order() {
+    let semaphore = Semaphore::fair(0, u32::MAX);
+    let order = Mutex::new(Vec::new());
+    thread::scope(|s| {
+        for id in 0..5 {
+            let (semaphore, order) = (&semaphore, &order);
+            s.spawn(move || {
+                semaphore.wait().unwrap();
+                order.lock().unwrap().push(id);
+            });
+            // Give each thread time to queue before the next one arrives
+            thread::sleep(Duration::from_millis(20));
+        }
+        for served in 1..=5 {
+            semaphore.signal().unwrap();
+            while order.lock().unwrap().len() < served {
+                thread::yield_now();
+            }
+        }
+    });
+    assert_eq!(*order.lock().unwrap(), [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn fair_multi_permit_waiter_is_not_overtaken() {
+    let semaphore = Semaphore::fair(0, 10);
+    thread::scope(|s| {
+        let bulk = s.spawn(|| semaphore.wait_n(3).unwrap());
+        thread::sleep(Duration::from_millis(20));
+        let single = s.spawn(|| semaphore.wait().unwrap());
+        thread::sleep(Duration::from_millis(20));
+
+        semaphore.signal().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(!single.is_finished());
+        // Queued threads cannot be overtaken by the non-blocking methods either
+        assert!(!semaphore.try_wait());
+
+        semaphore.signal_n(2).unwrap();
+        bulk.join().unwrap();
+        assert!(!single.is_finished());
+        semaphore.signal().unwrap();
+        single.join().unwrap();
+    });
+}
+
+#[test]
+fn fair_wait_is_bounded_under_contention() {
+    let semaphore = Semaphore::fair(1, 1);
+    let done = AtomicBool::new(false);
+    let max_wait = thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                while !done.load(Relaxed) {
+                    semaphore.wait().unwrap();
+                    thread::sleep(Duration::from_micros(100));
+                    semaphore.signal().unwrap();
+                }
+            });
+        }
+        let mut max_wait = Duration::ZERO;
+        for _ in 0..50 {
+            let start = Instant::now();
+            semaphore.wait().unwrap();
+            max_wait = max_wait.max(start.elapsed());
+            semaphore.signal().unwrap();
+        }
+        done.store(true, Relaxed);
+        max_wait
+    });
+    // Every thread ahead in the queue holds the permit for roughly 100us, so the wait is bounded by their number
+    assert!(max_wait < Duration::from_millis(500), "waited {max_wait:?} for a permit");
+}
+
+#[test]
+fn close_fails_blocked_threads() {
+    let semaphore = Semaphore::init(0, 1);
+    let full = Semaphore::init(1, 1);
+    thread::scope(|s| {
+        let waiter = s.spawn(|| semaphore.wait());
+        let timed = s.spawn(|| semaphore.wait_timeout(Duration::from_secs(10)));
+        let releaser = s.spawn(|| full.signal());
+        thread::sleep(Duration::from_millis(50));
+        semaphore.close();
+        full.close();
+        assert_eq!(waiter.join().unwrap(), Err(Closed));
+        assert_eq!(timed.join().unwrap(), Err(Closed));
+        assert_eq!(releaser.join().unwrap(), Err(Closed));
+    });
+}
+
+#[test]
+fn close_fails_later_calls() {
+    let semaphore = Semaphore::init(1, 2);
+    let permit = semaphore.acquire().unwrap();
+    semaphore.close();
+    assert!(semaphore.is_closed());
+    assert_eq!(semaphore.wait(), Err(Closed));
+    assert_eq!(semaphore.signal(), Err(Closed));
+    assert_eq!(semaphore.wait_timeout(Duration::from_millis(10)), Err(Closed));
+    assert!(semaphore.acquire().is_err());
+    assert!(!semaphore.try_signal());
+    assert!(!semaphore.try_wait());
+    // Dropping a permit of a closed semaphore must not panic
+    drop(permit);
+}
+
+#[test]
+fn introspection_reports_state() {
+    let semaphore = Semaphore::init(2, 3);
+    assert_eq!(semaphore.available(), 2);
+    assert_eq!(semaphore.max(), 3);
+    thread::scope(|s| {
+        semaphore.wait_n(2).unwrap();
+        let waiter = s.spawn(|| semaphore.wait());
+        while semaphore.waiting_acquirers() == 0 {
+            thread::yield_now();
+        }
+        assert_eq!(semaphore.available(), 0);
+        assert_eq!(semaphore.waiting_acquirers(), 1);
+        assert_eq!(semaphore.waiting_releasers(), 0);
+        assert_eq!(
+            format!("{semaphore:?}"),
+            "Semaphore { available: 0, max: 3, fair: false, closed: false, waiting_acquirers: 1, waiting_releasers: 0 }",
+        );
+        semaphore.signal().unwrap();
+        waiter.join().unwrap().unwrap();
+    });
+    assert_eq!(semaphore.waiting_acquirers(), 0);
+}
+
+#[test]
+fn add_capacity_releases_blocked_signal() {
+    let semaphore = Semaphore::init(2, 2);
+    thread::scope(|s| {
+        let releaser = s.spawn(|| semaphore.signal_n(2));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!releaser.is_finished());
+        semaphore.add_capacity(2);
+        releaser.join().unwrap().unwrap();
+    });
+    assert_eq!(semaphore.max(), 4);
+    assert_eq!(semaphore.available(), 4);
+}
+
+#[test]
+fn shrink_capacity_blocks_signal_until_drained() {
+    let semaphore = Semaphore::init(4, 4);
+    semaphore.shrink_capacity(2);
+    assert_eq!(semaphore.max(), 2);
+    assert!(!semaphore.try_signal());
+    thread::scope(|s| {
+        let releaser = s.spawn(|| semaphore.signal());
+        semaphore.wait_n(2).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        // The counter is back at the new maximum, so there is still no room
+        assert!(!releaser.is_finished());
+        semaphore.wait().unwrap();
+        releaser.join().unwrap().unwrap();
+    });
+    assert_eq!(semaphore.available(), 2);
+}
+
+#[test]
+fn set_max_allows_waiting_for_more_than_the_old_max() {
+    let semaphore = Semaphore::init(0, 2);
+    thread::scope(|s| {
+        let waiter = s.spawn(|| semaphore.wait_n(3));
+        semaphore.signal_n(2).unwrap();
+        semaphore.set_max(3);
+        semaphore.signal().unwrap();
+        waiter.join().unwrap().unwrap();
+    });
+}
+
+#[test]
+fn semaphore_in_a_static() {
+    static POOL_LIMIT: Semaphore = Semaphore::init(2, 2);
+    static FAIR: Semaphore = Semaphore::fair(0, 1);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let _permit = POOL_LIMIT.acquire().unwrap();
+                assert!(POOL_LIMIT.available() < 2);
+            });
+        }
+    });
+    assert_eq!(POOL_LIMIT.available(), 2);
+    assert!(FAIR.try_signal());
+    assert!(!FAIR.try_signal());
+}
+
+#[test]
+fn try_signal_wakes_waiters_while_the_queue_is_busy() {
+    // Many threads queueing and dequeueing keep the internal lock busy, the units released by `try_signal`
+    // must still reach the waiters
+    let semaphore = Semaphore::init(0, 4);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..2000 {
+                    semaphore.wait().unwrap();
+                }
+            });
+        }
+        for _ in 0..4 {
+            s.spawn(|| {
+                let mut released = 0;
+                while released < 2000 {
+                    if semaphore.try_signal() {
+                        released += 1;
+                    } else {
+                        thread::yield_now();
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(semaphore.available(), 0);
+}
+
+#[test]
+fn dropping_permits_after_shrink_does_not_block() {
+    let semaphore = Semaphore::init(2, 2);
+    let first = semaphore.acquire().unwrap();
+    let second = semaphore.acquire().unwrap();
+    semaphore.shrink_capacity(1);
+    drop(first);
+    assert_eq!(semaphore.available(), 1);
+    // The counter is at the new maximum, the unit of the second permit is dropped
+    drop(second);
+    assert_eq!(semaphore.available(), 1);
+    assert_eq!(semaphore.max(), 1);
+}
+
+#[test]
+fn dropping_owned_permits_after_shrink_does_not_block() {
+    let semaphore = Arc::new(Semaphore::init(2, 2));
+    let permits: Vec<_> = (0..2).map(|_| Arc::clone(&semaphore).acquire_owned().unwrap()).collect();
+    semaphore.set_max(0);
+    drop(permits);
+    assert_eq!(semaphore.available(), 0);
+    // Raising the maximum again lets permits be handed out as usual
+    semaphore.add_capacity(1);
+    assert!(semaphore.try_signal());
+    assert!(semaphore.acquire().is_ok());
+}